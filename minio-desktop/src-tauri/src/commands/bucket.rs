@@ -1,5 +1,5 @@
 use tauri::State;
-use crate::{AppState, error::{Result, AppError}, models::BucketInfo};
+use crate::{AppState, error::{Result, AppError}, models::{BucketInfo, LifecycleRule, VersioningStatus}};
 
 #[tauri::command]
 pub async fn list_buckets(state: State<'_, AppState>) -> Result<Vec<BucketInfo>> {
@@ -27,3 +27,50 @@ pub async fn delete_bucket(state: State<'_, AppState>, name: String) -> Result<(
     
     client.delete_bucket(&name).await
 }
+
+#[tauri::command]
+pub async fn get_bucket_versioning(state: State<'_, AppState>, name: String) -> Result<VersioningStatus> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.get_bucket_versioning(&name).await
+}
+
+/// Enable versioning, or suspend it when `enabled` is false
+#[tauri::command]
+pub async fn set_bucket_versioning(state: State<'_, AppState>, name: String, enabled: bool) -> Result<()> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.set_bucket_versioning(&name, enabled).await
+}
+
+#[tauri::command]
+pub async fn get_bucket_lifecycle(state: State<'_, AppState>, name: String) -> Result<Vec<LifecycleRule>> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.get_bucket_lifecycle(&name).await
+}
+
+/// Replace all lifecycle rules of a bucket
+#[tauri::command]
+pub async fn put_bucket_lifecycle(state: State<'_, AppState>, name: String, rules: Vec<LifecycleRule>) -> Result<()> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.put_bucket_lifecycle(&name, &rules).await
+}
+
+#[tauri::command]
+pub async fn delete_bucket_lifecycle(state: State<'_, AppState>, name: String) -> Result<()> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.delete_bucket_lifecycle(&name).await
+}
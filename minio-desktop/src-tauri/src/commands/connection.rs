@@ -1,5 +1,5 @@
 use tauri::State;
-use crate::{AppState, error::Result, models::{ConnectionConfig, BucketInfo}, minio::MinioClient};
+use crate::{AppState, db::Database, error::{Result, AppError}, models::{ConnectionConfig, ConnectionProfile, ProfileInput, BucketInfo, TlsPolicy}, minio::MinioClient};
 
 #[tauri::command]
 pub async fn test_connection(
@@ -8,6 +8,7 @@ pub async fn test_connection(
     access_key: String,
     secret_key: String,
     use_ssl: bool,
+    tls: Option<TlsPolicy>,
 ) -> Result<Vec<BucketInfo>> {
     let config = ConnectionConfig {
         endpoint,
@@ -15,6 +16,7 @@ pub async fn test_connection(
         access_key,
         secret_key,
         use_ssl,
+        tls: tls.unwrap_or_default(),
         last_connected: None,
     };
     
@@ -46,31 +48,289 @@ pub async fn save_connection(
     access_key: String,
     secret_key: String,
     use_ssl: bool,
+    tls: Option<TlsPolicy>,
 ) -> Result<()> {
-    let config = ConnectionConfig {
-        endpoint,
-        port,
-        access_key,
-        secret_key,
-        use_ssl,
-        last_connected: Some(chrono::Utc::now()),
+    let now = chrono::Utc::now();
+    
+    // Update the active (or default) profile, creating one on first use
+    let active_id = state.active_profile.lock().await.clone();
+    let db = state.db.lock().await;
+    let existing = match active_id {
+        Some(id) => db.get_profile(&id)?,
+        None => db.get_default_profile()?,
+    };
+    
+    let profile = match existing {
+        Some(mut profile) => {
+            profile.endpoint = endpoint;
+            profile.port = port;
+            profile.access_key = access_key;
+            profile.secret_key = secret_key;
+            profile.use_ssl = use_ssl;
+            if let Some(tls) = tls {
+                profile.tls = tls;
+            }
+            profile.updated_at = now;
+            profile
+        }
+        None => ConnectionProfile {
+            profile_id: uuid::Uuid::new_v4().to_string(),
+            name: "Default".to_string(),
+            endpoint,
+            port,
+            access_key,
+            secret_key,
+            use_ssl,
+            tls: tls.unwrap_or_default(),
+            is_default: true,
+            last_connected: None,
+            created_at: now,
+            updated_at: now,
+        },
     };
     
-    // Save to database
+    db.save_profile(&profile)?;
+    drop(db);
+    
+    activate_profile(&state, &profile).await
+}
+
+#[tauri::command]
+pub async fn get_connection(state: State<'_, AppState>) -> Result<Option<ConnectionConfig>> {
+    Ok(load_active_profile(&state).await?.map(|p| p.to_config()))
+}
+
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, AppState>) -> Result<Vec<ConnectionProfile>> {
     let db = state.db.lock().await;
-    db.save_connection(&config)?;
+    db.list_profiles()
+}
+
+#[tauri::command]
+pub async fn get_active_profile(state: State<'_, AppState>) -> Result<Option<ConnectionProfile>> {
+    load_active_profile(&state).await
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    state: State<'_, AppState>,
+    name: String,
+    input: ProfileInput,
+) -> Result<ConnectionProfile> {
+    let name = validate_profile_name(&name)?;
+    let db = state.db.lock().await;
+    ensure_profile_name_free(&db, &name)?;
+    
+    let now = chrono::Utc::now();
+    let profile = ConnectionProfile {
+        profile_id: uuid::Uuid::new_v4().to_string(),
+        name,
+        endpoint: input.endpoint,
+        port: input.port,
+        access_key: input.access_key,
+        secret_key: input.secret_key,
+        use_ssl: input.use_ssl,
+        tls: input.tls.unwrap_or_default(),
+        // The first profile becomes the default one
+        is_default: db.get_default_profile()?.is_none(),
+        last_connected: None,
+        created_at: now,
+        updated_at: now,
+    };
+    
+    db.save_profile(&profile)?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn update_profile(
+    state: State<'_, AppState>,
+    profile_id: String,
+    input: ProfileInput,
+) -> Result<ConnectionProfile> {
+    let db = state.db.lock().await;
+    let mut profile = db.get_profile(&profile_id)?
+        .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?;
+    
+    profile.endpoint = input.endpoint;
+    profile.port = input.port;
+    profile.access_key = input.access_key;
+    profile.secret_key = input.secret_key;
+    profile.use_ssl = input.use_ssl;
+    if let Some(tls) = input.tls {
+        profile.tls = tls;
+    }
+    profile.updated_at = chrono::Utc::now();
+    db.save_profile(&profile)?;
     drop(db);
     
-    // Create and store MinIO client
-    let client = MinioClient::new(config).await?;
+    // Rebuild the client if the edited profile is the one in use
+    let is_active = state.active_profile.lock().await.as_deref() == Some(profile_id.as_str());
+    if is_active {
+        activate_profile(&state, &profile).await?;
+    }
+    
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn rename_profile(state: State<'_, AppState>, profile_id: String, name: String) -> Result<()> {
+    let name = validate_profile_name(&name)?;
+    let db = state.db.lock().await;
+    let profile = db.get_profile(&profile_id)?
+        .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?;
+    
+    if profile.name != name {
+        ensure_profile_name_free(&db, &name)?;
+    }
+    
+    db.rename_profile(&profile_id, &name)
+}
+
+#[tauri::command]
+pub async fn delete_profile(state: State<'_, AppState>, profile_id: String) -> Result<()> {
+    {
+        let db = state.db.lock().await;
+        db.get_profile(&profile_id)?
+            .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?;
+        db.delete_profile(&profile_id)?;
+    }
+    
+    // Disconnect if the deleted profile was in use. The client lock is
+    // taken first, as everywhere the pair is read or written.
     let mut minio_client = state.minio_client.lock().await;
-    *minio_client = Some(client);
+    let mut active = state.active_profile.lock().await;
+    if active.as_deref() == Some(profile_id.as_str()) {
+        *active = None;
+        *minio_client = None;
+    }
     
     Ok(())
 }
 
 #[tauri::command]
-pub async fn get_connection(state: State<'_, AppState>) -> Result<Option<ConnectionConfig>> {
+pub async fn duplicate_profile(
+    state: State<'_, AppState>,
+    profile_id: String,
+    name: Option<String>,
+) -> Result<ConnectionProfile> {
+    let db = state.db.lock().await;
+    let source = db.get_profile(&profile_id)?
+        .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?;
+    
+    let name = match name {
+        Some(n) => {
+            let name = validate_profile_name(&n)?;
+            ensure_profile_name_free(&db, &name)?;
+            name
+        }
+        None => {
+            // Pick the first free "<name> (copy N)" suffix
+            let mut candidate = format!("{} (copy)", source.name);
+            let mut n = 2;
+            while db.profile_name_exists(&candidate)? {
+                candidate = format!("{} (copy {})", source.name, n);
+                n += 1;
+            }
+            candidate
+        }
+    };
+    
+    let now = chrono::Utc::now();
+    let profile = ConnectionProfile {
+        profile_id: uuid::Uuid::new_v4().to_string(),
+        name,
+        is_default: false,
+        last_connected: None,
+        created_at: now,
+        updated_at: now,
+        ..source
+    };
+    
+    db.save_profile(&profile)?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn set_default_profile(state: State<'_, AppState>, profile_id: String) -> Result<()> {
+    let db = state.db.lock().await;
+    db.get_profile(&profile_id)?
+        .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?;
+    db.set_default_profile(&profile_id)
+}
+
+#[tauri::command]
+pub async fn switch_profile(state: State<'_, AppState>, profile_id: String) -> Result<ConnectionProfile> {
+    let profile = {
+        let db = state.db.lock().await;
+        db.get_profile(&profile_id)?
+            .ok_or_else(|| AppError::ProfileNotFound(profile_id.clone()))?
+    };
+    
+    activate_profile(&state, &profile).await?;
+    println!("Switched to connection profile: {}", profile.name);
+    
+    Ok(profile)
+}
+
+/// Load the profile in use, falling back to the default one
+async fn load_active_profile(state: &AppState) -> Result<Option<ConnectionProfile>> {
+    let active_id = state.active_profile.lock().await.clone();
     let db = state.db.lock().await;
-    db.get_connection()
+    match active_id {
+        Some(id) => db.get_profile(&id),
+        None => db.get_default_profile(),
+    }
+}
+
+/// Connect the default profile, if there is one
+pub(crate) async fn connect_default_profile(state: &AppState) -> Result<()> {
+    let profile = {
+        let db = state.db.lock().await;
+        db.get_default_profile()?
+    };
+    
+    if let Some(profile) = profile {
+        activate_profile(state, &profile).await?;
+        println!("Connected to default profile: {}", profile.name);
+    }
+    
+    Ok(())
+}
+
+/// Build a MinIO client for the profile and make it the active connection
+async fn activate_profile(state: &AppState, profile: &ConnectionProfile) -> Result<()> {
+    let client = MinioClient::new(profile.to_config()).await?;
+    
+    {
+        // Switch both under the client lock so queued tasks never see the
+        // new client paired with the old profile
+        let mut minio_client = state.minio_client.lock().await;
+        *minio_client = Some(client);
+        *state.active_profile.lock().await = Some(profile.profile_id.clone());
+    }
+    
+    {
+        let db = state.db.lock().await;
+        db.touch_profile(&profile.profile_id)?;
+    }
+    
+    // Queued transfers can run now that there is a connection
+    state.transfer_manager.schedule();
+    Ok(())
+}
+
+fn validate_profile_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidConfig("Profile name cannot be empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+fn ensure_profile_name_free(db: &Database, name: &str) -> Result<()> {
+    if db.profile_name_exists(name)? {
+        return Err(AppError::InvalidConfig(format!("Profile '{}' already exists", name)));
+    }
+    Ok(())
 }
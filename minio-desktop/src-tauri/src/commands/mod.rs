@@ -2,5 +2,7 @@ pub mod connection;
 pub mod bucket;
 pub mod object;
 pub mod transfer;
+pub mod multipart;
 pub mod streaming;
 pub mod encryption;
+pub mod vault;
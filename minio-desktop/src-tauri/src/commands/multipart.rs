@@ -0,0 +1,27 @@
+use tauri::State;
+use crate::{AppState, error::Result, models::MultipartUploadInfo};
+
+#[tauri::command]
+pub async fn list_multipart_uploads(state: State<'_, AppState>, bucket: String) -> Result<Vec<MultipartUploadInfo>> {
+    state.transfer_manager.multipart_uploads(&bucket).await
+}
+
+#[tauri::command]
+pub async fn abort_multipart_upload(
+    state: State<'_, AppState>,
+    bucket: String,
+    key: String,
+    upload_id: String,
+) -> Result<()> {
+    println!("Aborting multipart upload {} of {}/{}", upload_id, bucket, key);
+    state.transfer_manager.abort_multipart_upload(&bucket, &key, &upload_id).await
+}
+
+#[tauri::command]
+pub async fn abort_stale_multipart_uploads(
+    state: State<'_, AppState>,
+    bucket: String,
+    older_than_hours: u32,
+) -> Result<usize> {
+    state.transfer_manager.abort_stale_uploads(&bucket, older_than_hours).await
+}
@@ -1,5 +1,6 @@
 use tauri::State;
-use crate::{AppState, error::{Result, AppError}, models::ObjectInfo};
+use crate::{AppState, error::{Result, AppError}, models::{ObjectInfo, TransferOptions, ObjectListPage, ObjectListBatch, ObjectVersionPage}, minio::ListOptions};
+use tokio_util::sync::CancellationToken;
 use aws_sdk_s3::presigning::PresigningConfig;
 use std::time::Duration;
 
@@ -11,6 +12,7 @@ pub async fn get_presigned_url(
     bucket: String,
     object_key: String,
     expires_in_seconds: Option<u64>,
+    version_id: Option<String>,
 ) -> Result<String> {
     let client_guard = state.minio_client.lock().await;
     let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
@@ -27,6 +29,7 @@ pub async fn get_presigned_url(
         .get_object()
         .bucket(&bucket)
         .key(&object_key)
+        .set_version_id(version_id)
         .presigned(presigning_config)
         .await
         .map_err(|e| {
@@ -42,47 +45,166 @@ pub async fn list_objects(
     state: State<'_, AppState>,
     bucket: String,
     prefix: Option<String>,
+    start_after: Option<String>,
 ) -> Result<Vec<ObjectInfo>> {
     let client_guard = state.minio_client.lock().await;
     let client = client_guard.as_ref()
         .ok_or(AppError::NotConnected)?;
     
-    client.list_objects(&bucket, prefix.as_deref()).await
+    client.list_objects(&bucket, prefix.as_deref(), start_after.as_deref()).await
 }
 
 #[tauri::command]
-pub async fn upload_file(
+pub async fn list_objects_page(
     state: State<'_, AppState>,
-    local_path: String,
     bucket: String,
-    object_key: String,
-    use_encryption: Option<bool>,
-    encryption_key: Option<String>,
+    prefix: Option<String>,
+    continuation_token: Option<String>,
+    start_after: Option<String>,
+    max_keys: Option<i32>,
+    recursive: Option<bool>,
+) -> Result<ObjectListPage> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
+    
+    client.list_objects_page(&bucket, &ListOptions {
+        prefix: prefix.as_deref(),
+        recursive: recursive.unwrap_or(false),
+        continuation_token: continuation_token.as_deref(),
+        start_after: start_after.as_deref(),
+        max_keys,
+    }).await
+}
+
+/// Start a listing that is delivered in batches through `object-list-batch`
+/// events. Returns the listing id carried by every batch.
+#[tauri::command]
+pub async fn stream_objects(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    bucket: String,
+    prefix: Option<String>,
+    start_after: Option<String>,
+    recursive: Option<bool>,
+    batch_size: Option<i32>,
 ) -> Result<String> {
-    let task_id = uuid::Uuid::new_v4().to_string();
-    let task_id_clone = task_id.clone();
-    let bucket_clone = bucket.clone();
-    let object_key_clone = object_key.clone();
-    let local_path_clone = local_path.clone();
-    let use_encryption = use_encryption.unwrap_or(false);
+    if state.minio_client.lock().await.is_none() {
+        return Err(AppError::NotConnected);
+    }
+    
+    let listing_id = uuid::Uuid::new_v4().to_string();
+    let cancel_token = CancellationToken::new();
+    state.listings.lock().await.insert(listing_id.clone(), cancel_token.clone());
     
-    let transfer_manager = state.transfer_manager.clone();
+    let minio_client = state.minio_client.clone();
+    let listings = state.listings.clone();
+    let listing_id_clone = listing_id.clone();
+    let recursive = recursive.unwrap_or(false);
+    let batch_size = batch_size.unwrap_or(1000).clamp(1, 1000);
     
-    // Start upload task in background
     tokio::spawn(async move {
-        if let Err(e) = transfer_manager.lock().await.start_upload(
-            task_id_clone, 
-            local_path_clone, 
-            bucket_clone, 
-            object_key_clone,
-            use_encryption,
-            encryption_key,
-        ).await {
-            eprintln!("Upload task failed: {}", e);
+        let mut continuation_token: Option<String> = None;
+        let mut total = 0usize;
+        
+        loop {
+            if cancel_token.is_cancelled() {
+                println!("Listing {} cancelled after {} objects", listing_id_clone, total);
+                break;
+            }
+            
+            let page = {
+                let client_guard = minio_client.lock().await;
+                match client_guard.as_ref() {
+                    Some(client) => client.list_objects_page(&bucket, &ListOptions {
+                        prefix: prefix.as_deref(),
+                        recursive,
+                        continuation_token: continuation_token.as_deref(),
+                        start_after: start_after.as_deref(),
+                        max_keys: Some(batch_size),
+                    }).await,
+                    None => Err(AppError::NotConnected),
+                }
+            };
+            
+            let batch = match page {
+                Ok(page) => {
+                    total += page.objects.len();
+                    continuation_token = page.next_continuation_token.filter(|_| page.is_truncated);
+                    ObjectListBatch {
+                        listing_id: listing_id_clone.clone(),
+                        objects: page.objects,
+                        total,
+                        done: continuation_token.is_none(),
+                        error: None,
+                    }
+                }
+                Err(e) => ObjectListBatch {
+                    listing_id: listing_id_clone.clone(),
+                    objects: vec![],
+                    total,
+                    done: true,
+                    error: Some(e.to_string()),
+                },
+            };
+            
+            let done = batch.done;
+            if let Err(e) = window.emit("object-list-batch", batch) {
+                eprintln!("Failed to emit listing batch: {}", e);
+                break;
+            }
+            if done {
+                break;
+            }
         }
+        
+        listings.lock().await.remove(&listing_id_clone);
     });
     
-    Ok(task_id)
+    Ok(listing_id)
+}
+
+#[tauri::command]
+pub async fn cancel_object_listing(state: State<'_, AppState>, listing_id: String) -> Result<()> {
+    if let Some(token) = state.listings.lock().await.remove(&listing_id) {
+        token.cancel();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn upload_file(
+    state: State<'_, AppState>,
+    local_path: String,
+    bucket: String,
+    object_key: String,
+    options: Option<TransferOptions>,
+) -> Result<String> {
+    // Queue the upload; the transfer scheduler starts it when a slot is free
+    state.transfer_manager
+        .enqueue_upload(local_path, bucket, object_key, options.unwrap_or_default())
+        .await
+}
+
+#[tauri::command]
+pub async fn upload_folder(
+    state: State<'_, AppState>,
+    local_path: String,
+    bucket: String,
+    prefix: Option<String>,
+    follow_symlinks: Option<bool>,
+    options: Option<TransferOptions>,
+) -> Result<String> {
+    // Returns the job id; each file becomes a queued child task
+    state.transfer_manager
+        .enqueue_folder_upload(
+            local_path,
+            bucket,
+            prefix.unwrap_or_default(),
+            follow_symlinks.unwrap_or(false),
+            options.unwrap_or_default(),
+        )
+        .await
 }
 
 #[tauri::command]
@@ -91,39 +213,98 @@ pub async fn download_file(
     bucket: String,
     object_key: String,
     local_path: String,
+    options: Option<TransferOptions>,
+) -> Result<String> {
+    // Queue the download; the transfer scheduler starts it when a slot is free
+    state.transfer_manager
+        .enqueue_download(bucket, object_key, local_path, options.unwrap_or_default())
+        .await
+}
+
+#[tauri::command]
+pub async fn download_folder(
+    state: State<'_, AppState>,
+    bucket: String,
+    prefix: String,
+    local_path: String,
+    options: Option<TransferOptions>,
 ) -> Result<String> {
-    let task_id = uuid::Uuid::new_v4().to_string();
+    // Returns the job id; each object becomes a queued child task
+    state.transfer_manager
+        .enqueue_folder_download(bucket, prefix, local_path, options.unwrap_or_default())
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_object(
+    state: State<'_, AppState>,
+    bucket: String,
+    key: String,
+) -> Result<()> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
     
-    // Clone necessary values to move into the async block
-    let task_id_clone = task_id.clone();
-    let bucket_clone = bucket.clone();
-    let object_key_clone = object_key.clone();
-    let local_path_clone = local_path.clone();
+    client.delete_object(&bucket, &key).await
+}
+
+/// One page of versions and delete markers under `prefix`
+#[tauri::command]
+pub async fn list_object_versions(
+    state: State<'_, AppState>,
+    bucket: String,
+    prefix: Option<String>,
+    key_marker: Option<String>,
+    version_id_marker: Option<String>,
+    max_keys: Option<i32>,
+) -> Result<ObjectVersionPage> {
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
     
-    // Clone the Arc to pass to the async block
-    let transfer_manager = state.transfer_manager.clone();
+    client.list_object_versions_page(
+        &bucket,
+        prefix.as_deref(),
+        key_marker.as_deref(),
+        version_id_marker.as_deref(),
+        max_keys,
+    ).await
+}
+
+/// Copy an old version over the latest one. SSE-C objects need their key.
+#[tauri::command]
+pub async fn restore_object_version(
+    state: State<'_, AppState>,
+    bucket: String,
+    key: String,
+    version_id: String,
+    use_encryption: Option<bool>,
+    encryption_key: Option<String>,
+) -> Result<()> {
+    let sse_c = state.transfer_manager
+        .resolve_key(use_encryption.unwrap_or(false), encryption_key.as_deref())
+        .await?;
     
-    // Start download task in background
-    tokio::spawn(async move {
-        if let Err(e) = transfer_manager.lock().await.start_download(task_id_clone, bucket_clone, object_key_clone, local_path_clone).await {
-            eprintln!("Download task failed: {}", e);
-        }
-    });
+    let client_guard = state.minio_client.lock().await;
+    let client = client_guard.as_ref()
+        .ok_or(AppError::NotConnected)?;
     
-    Ok(task_id)
+    client.restore_object_version(&bucket, &key, &version_id, sse_c.as_ref()).await
 }
 
+/// Permanently delete one version or delete marker
 #[tauri::command]
-pub async fn delete_object(
+pub async fn delete_object_version(
     state: State<'_, AppState>,
     bucket: String,
     key: String,
+    version_id: String,
 ) -> Result<()> {
     let client_guard = state.minio_client.lock().await;
     let client = client_guard.as_ref()
         .ok_or(AppError::NotConnected)?;
     
-    client.delete_object(&bucket, &key).await
+    client.delete_object_version(&bucket, &key, &version_id).await
 }
 
 #[tauri::command]
@@ -131,6 +312,7 @@ pub async fn get_object_data(
     state: State<'_, AppState>,
     bucket: String,
     object_key: String,
+    version_id: Option<String>,
 ) -> Result<Vec<u8>> {
     println!("[get_object_data] Starting for bucket: {}, key: {}", bucket, object_key);
     
@@ -154,7 +336,8 @@ pub async fn get_object_data(
         .get_client()
         .get_object()
         .bucket(&bucket)
-        .key(&object_key);
+        .key(&object_key)
+        .set_version_id(version_id);
     
     // Add SSE-C headers if encryption key exists
     if let Some(key) = encryption_key {
@@ -6,6 +6,7 @@ pub async fn get_stream_url(
     state: State<'_, AppState>,
     bucket: String,
     object_key: String,
+    version_id: Option<String>,
 ) -> Result<String> {
     eprintln!("[get_stream_url] Getting stream URL for: {}/{}", bucket, object_key);
     
@@ -37,7 +38,7 @@ pub async fn get_stream_url(
     
     // Verify the stream server is actually running
     let server = server_guard.as_ref().unwrap();
-    let token = server.create_token(bucket, object_key).await;
+    let token = server.create_token(bucket, object_key, version_id).await;
     let port = server.get_port();
     
     // Create URL
@@ -1,76 +1,110 @@
 use tauri::State;
 use serde::{Deserialize};
-use crate::{AppState, error::Result, models::TransferTask};
+use crate::{AppState, error::Result, models::{TransferTask, TransferJob, TransferSettings, TransferStats}};
 
 #[tauri::command]
 pub async fn get_transfer_tasks(state: State<'_, AppState>) -> Result<Vec<TransferTask>> {
-    let db = state.db.lock().await;
-    db.get_all_tasks()
+    state.transfer_manager.tasks().await
 }
 
 #[tauri::command]
 pub async fn pause_task(state: State<'_, AppState>, task_id: String) -> Result<()> {
     println!("Pausing task: {}", task_id);
-    let db = state.db.lock().await;
-    let mut task = db.get_task(&task_id)?
-        .ok_or_else(|| crate::error::AppError::TaskNotFound(task_id.clone()))?;
-    
-    println!("Current task status: {:?}", task.status);
-    if task.status == crate::models::TaskStatus::Running {
-        task.status = crate::models::TaskStatus::Paused;
-        db.update_task_status(&task_id, crate::models::TaskStatus::Paused)?;
-        println!("Task paused successfully");
-    }
-    
-    Ok(())
+    state.transfer_manager.pause_task(&task_id).await
 }
 
 #[tauri::command]
 pub async fn resume_task(state: State<'_, AppState>, task_id: String) -> Result<()> {
     println!("Resuming task: {}", task_id);
-    let db = state.db.lock().await;
-    let task = db.get_task(&task_id)?
-        .ok_or_else(|| crate::error::AppError::TaskNotFound(task_id.clone()))?;
-    
-    println!("Current task status: {:?}", task.status);
-    if task.status == crate::models::TaskStatus::Paused {
-        // For now, we'll update the status in the database
-        // In a full implementation, we would restart the actual transfer
-        db.update_task_status(&task_id, crate::models::TaskStatus::Running)?;
-        println!("Task resumed successfully");
-    }
-    
-    Ok(())
+    state.transfer_manager.resume_task(&task_id).await
+}
+
+#[tauri::command]
+pub async fn resume_download(state: State<'_, AppState>, task_id: String) -> Result<()> {
+    println!("Resuming download: {}", task_id);
+    state.transfer_manager.resume_download(&task_id).await
 }
 
 #[tauri::command]
 pub async fn cancel_task(state: State<'_, AppState>, task_id: String) -> Result<()> {
     println!("Cancelling task: {}", task_id);
-    let db = state.db.lock().await;
-    let result = db.update_task_status(&task_id, crate::models::TaskStatus::Cancelled)?;
-    println!("Task cancelled successfully");
-    Ok(result)
+    state.transfer_manager.cancel_task(&task_id).await
 }
 
 #[tauri::command]
 pub async fn cancel_all_tasks(state: State<'_, AppState>) -> Result<()> {
     println!("Cancelling all tasks");
-    let db = state.db.lock().await;
-    let tasks = db.get_all_tasks()?;
-    
-    let mut cancelled_count = 0;
-    for task in tasks {
-        // Only cancel tasks that are running, paused, or pending
-        if matches!(task.status, crate::models::TaskStatus::Running | crate::models::TaskStatus::Paused | crate::models::TaskStatus::Pending) {
-            db.update_task_status(&task.task_id, crate::models::TaskStatus::Cancelled)?;
-            cancelled_count += 1;
-        }
-    }
-    
+    let cancelled_count = state.transfer_manager.cancel_all().await?;
     println!("Cancelled {} tasks", cancelled_count);
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_transfer_jobs(state: State<'_, AppState>) -> Result<Vec<TransferJob>> {
+    let db = state.db.lock().await;
+    db.get_jobs()
+}
+
+#[tauri::command]
+pub async fn get_job_tasks(state: State<'_, AppState>, job_id: String) -> Result<Vec<TransferTask>> {
+    let db = state.db.lock().await;
+    db.get_job_tasks(&job_id)
+}
+
+#[tauri::command]
+pub async fn pause_job(state: State<'_, AppState>, job_id: String) -> Result<()> {
+    println!("Pausing job: {}", job_id);
+    state.transfer_manager.pause_job(&job_id).await
+}
+
+#[tauri::command]
+pub async fn resume_job(state: State<'_, AppState>, job_id: String) -> Result<()> {
+    println!("Resuming job: {}", job_id);
+    state.transfer_manager.resume_job(&job_id).await
+}
+
+#[tauri::command]
+pub async fn cancel_job(state: State<'_, AppState>, job_id: String) -> Result<()> {
+    println!("Cancelling job: {}", job_id);
+    state.transfer_manager.cancel_job(&job_id).await
+}
+
+#[tauri::command]
+pub async fn delete_job(state: State<'_, AppState>, job_id: String) -> Result<()> {
+    println!("Deleting job: {}", job_id);
+    state.transfer_manager.delete_job(&job_id).await
+}
+
+#[tauri::command]
+pub async fn set_task_priority(state: State<'_, AppState>, task_id: String, priority: i32) -> Result<()> {
+    state.transfer_manager.set_priority(&task_id, priority).await
+}
+
+#[tauri::command]
+pub async fn set_task_bandwidth_limit(state: State<'_, AppState>, task_id: String, limit: Option<u64>) -> Result<()> {
+    state.transfer_manager.set_bandwidth_limit(&task_id, limit).await
+}
+
+#[tauri::command]
+pub async fn reorder_tasks(state: State<'_, AppState>, task_ids: Vec<String>) -> Result<()> {
+    state.transfer_manager.reorder(&task_ids).await
+}
+
+#[tauri::command]
+pub async fn get_transfer_stats(state: State<'_, AppState>) -> Result<TransferStats> {
+    Ok(state.transfer_manager.stats().await)
+}
+
+#[tauri::command]
+pub async fn get_transfer_settings(state: State<'_, AppState>) -> Result<TransferSettings> {
+    Ok(state.transfer_manager.settings().await)
+}
+
+#[tauri::command]
+pub async fn update_transfer_settings(state: State<'_, AppState>, settings: TransferSettings) -> Result<()> {
+    state.transfer_manager.update_settings(settings).await
+}
+
 #[tauri::command]
 pub async fn delete_task(state: State<'_, AppState>, task_id: String) -> Result<()> {
     println!("Deleting task: {}", task_id);
@@ -88,8 +122,8 @@ pub async fn delete_completed_tasks(state: State<'_, AppState>) -> Result<()> {
     
     let mut deleted_count = 0;
     for task in tasks {
-        // Delete completed, failed, and cancelled tasks
-        if matches!(task.status, crate::models::TaskStatus::Completed | crate::models::TaskStatus::Failed | crate::models::TaskStatus::Cancelled) {
+        // Delete completed, skipped, failed, cancelled and corrupted tasks
+        if matches!(task.status, crate::models::TaskStatus::Completed | crate::models::TaskStatus::Skipped | crate::models::TaskStatus::Failed | crate::models::TaskStatus::Cancelled | crate::models::TaskStatus::Corrupted) {
             db.delete_task(&task.task_id)?;
             deleted_count += 1;
         }
@@ -0,0 +1,79 @@
+use tauri::State;
+use crate::{AppState, error::{Result, AppError}, models::VaultStatus, vault};
+
+#[tauri::command]
+pub async fn get_vault_status(state: State<'_, AppState>) -> Result<VaultStatus> {
+    let db = state.db.lock().await;
+    Ok(db.vault_status())
+}
+
+#[tauri::command]
+pub async fn setup_vault(state: State<'_, AppState>, passphrase: String) -> Result<VaultStatus> {
+    if passphrase.chars().count() < 8 {
+        return Err(AppError::InvalidConfig("Passphrase must be at least 8 characters".to_string()));
+    }
+
+    let salt = vault::generate_salt();
+    let params = vault::KdfParams::default();
+    let key = derive_key_blocking(passphrase, salt.clone(), params.clone()).await?;
+
+    let db = state.db.lock().await;
+    let encrypted = db.setup_vault(key, &salt, &params)?;
+    println!("Vault set up, {} existing secrets encrypted", encrypted);
+
+    Ok(db.vault_status())
+}
+
+#[tauri::command]
+pub async fn unlock_vault(state: State<'_, AppState>, passphrase: String) -> Result<VaultStatus> {
+    let (salt, params) = {
+        let db = state.db.lock().await;
+        db.vault_kdf_params()?
+            .ok_or_else(|| AppError::InvalidConfig("Vault is not set up".to_string()))?
+    };
+
+    let key = derive_key_blocking(passphrase, salt, params).await?;
+
+    let status = {
+        let db = state.db.lock().await;
+        db.unlock_vault(key)?;
+        db.vault_status()
+    };
+
+    // Secrets are readable again: connect the default profile if nothing is connected yet
+    if state.minio_client.lock().await.is_none() {
+        if let Err(e) = super::connection::connect_default_profile(&state).await {
+            eprintln!("Failed to connect default profile after unlock: {}", e);
+        }
+    }
+
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, AppState>) -> Result<()> {
+    let db = state.db.lock().await;
+    db.lock_vault();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_vault_idle_timeout(state: State<'_, AppState>, seconds: u64) -> Result<()> {
+    if seconds < 60 {
+        return Err(AppError::InvalidConfig("Idle timeout must be at least 60 seconds".to_string()));
+    }
+
+    let db = state.db.lock().await;
+    db.set_vault_idle_timeout(seconds)
+}
+
+/// Argon2 is deliberately slow; keep it off the async runtime threads
+async fn derive_key_blocking(
+    passphrase: String,
+    salt: Vec<u8>,
+    params: vault::KdfParams,
+) -> Result<zeroize::Zeroizing<[u8; 32]>> {
+    tokio::task::spawn_blocking(move || vault::derive_key(&passphrase, &salt, &params))
+        .await
+        .map_err(|e| AppError::Other(e.to_string()))?
+}
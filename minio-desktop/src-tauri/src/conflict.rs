@@ -0,0 +1,68 @@
+// Naming and comparison rules for transfers whose target already exists.
+
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+
+/// Numbered names tried before a keep-both transfer gives up
+pub const MAX_NUMBERED_NAMES: u32 = 1000;
+
+/// `report.pdf` becomes `report (n).pdf`; names without an extension, and
+/// dotfiles such as `.env`, get the number at the end
+pub fn numbered_name(name: &str, n: u32) -> String {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{} ({}){}", &name[..dot], n, &name[dot..]),
+        _ => format!("{} ({})", name, n),
+    }
+}
+
+/// Object key with its last segment numbered
+pub fn numbered_key(key: &str, n: u32) -> String {
+    match key.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/{}", dir, numbered_name(name, n)),
+        None => numbered_name(key, n),
+    }
+}
+
+/// Local path with its file name numbered
+pub fn numbered_path(path: &Path, n: u32) -> PathBuf {
+    let name = path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(numbered_name(&name, n))
+}
+
+/// Whether a source modified at `source` should replace a target modified
+/// at `target`. When either time is unknown the source is assumed newer.
+pub fn source_is_newer(source: Option<DateTime<Utc>>, target: Option<DateTime<Utc>>) -> bool {
+    match (source, target) {
+        (Some(source), Some(target)) => source > target,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_numbered_names() {
+        assert_eq!(numbered_name("report.pdf", 1), "report (1).pdf");
+        assert_eq!(numbered_name("archive.tar.gz", 2), "archive.tar (2).gz");
+        assert_eq!(numbered_name("README", 3), "README (3)");
+        assert_eq!(numbered_name(".env", 1), ".env (1)");
+        assert_eq!(numbered_key("docs/v1.2/notes.txt", 1), "docs/v1.2/notes (1).txt");
+        assert_eq!(numbered_path(Path::new("dir.d/file"), 4), Path::new("dir.d/file (4)"));
+    }
+
+    #[test]
+    fn test_source_is_newer() {
+        let earlier = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert!(source_is_newer(Some(later), Some(earlier)));
+        assert!(!source_is_newer(Some(earlier), Some(later)));
+        assert!(!source_is_newer(Some(earlier), Some(earlier)));
+        assert!(source_is_newer(None, Some(earlier)));
+        assert!(source_is_newer(Some(earlier), None));
+    }
+}
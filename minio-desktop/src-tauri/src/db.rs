@@ -1,35 +1,59 @@
 use rusqlite::{Connection, params};
 use crate::error::Result;
-use crate::models::{ConnectionConfig, TransferTask, TaskType, TaskStatus, CompletedPart, EncryptionKey};
+use crate::models::{ConnectionProfile, ConflictPolicy, TransferTask, TransferJob, TransferSettings, TaskType, TaskStatus, CompletedPart, EncryptionKey, VaultStatus};
+use crate::error::AppError;
+use crate::vault::{self, KdfParams, Vault};
+use base64::{Engine as _, engine::general_purpose};
 use chrono::Utc;
+use zeroize::Zeroizing;
+
+/// Associated data for the vault's passphrase check value
+const VAULT_VERIFIER_CONTEXT: &str = "vault_meta.verifier";
+
+/// Column list shared by the transfer task queries, in `row_to_task` order
+const TASK_COLUMNS: &str = "task_id, task_type, file_name, local_path, bucket_name, object_key, file_size,
+    upload_id, part_size, total_parts, completed_parts, transferred_bytes, status,
+    error_message, use_encryption, created_at, updated_at, completed_at, priority, queue_position,
+    remote_etag, job_id, retry_count, bandwidth_limit, active_ms, encryption_key_md5, conflict_policy,
+    source_fingerprint, version_id, profile_id";
 
 pub struct Database {
     conn: Connection,
+    vault: Vault,
 }
 
 impl Database {
     pub fn new(path: &str) -> Result<Self> {
         let conn = Connection::open(path)?;
-        let db = Database { conn };
+        let db = Database { conn, vault: Vault::new(false) };
         db.init()?;
+        db.load_vault_settings()?;
         Ok(db)
     }
     
     fn init(&self) -> Result<()> {
-        // Create connection_config table
+        // Create connection_profiles table
         self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS connection_config (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
+            "CREATE TABLE IF NOT EXISTS connection_profiles (
+                profile_id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
                 endpoint TEXT NOT NULL,
                 port INTEGER NOT NULL,
                 access_key TEXT NOT NULL,
                 secret_key TEXT NOT NULL,
                 use_ssl INTEGER NOT NULL,
-                last_connected TEXT
+                tls_policy TEXT,
+                is_default INTEGER NOT NULL DEFAULT 0,
+                last_connected TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
             )",
             [],
         )?;
         
+        self.add_column_if_missing("connection_profiles", "tls_policy", "TEXT")?;
+        self.migrate_legacy_connection()?;
+        
         // Create transfer_tasks table
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS transfer_tasks (
@@ -55,11 +79,50 @@ impl Database {
             [],
         )?;
         
+        self.add_column_if_missing("transfer_tasks", "priority", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("transfer_tasks", "queue_position", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("transfer_tasks", "remote_etag", "TEXT")?;
+        self.add_column_if_missing("transfer_tasks", "job_id", "TEXT")?;
+        self.add_column_if_missing("transfer_tasks", "retry_count", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("transfer_tasks", "bandwidth_limit", "INTEGER")?;
+        self.add_column_if_missing("transfer_tasks", "active_ms", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("transfer_tasks", "encryption_key_md5", "TEXT")?;
+        self.add_column_if_missing("transfer_tasks", "conflict_policy", "TEXT NOT NULL DEFAULT 'overwrite'")?;
+        self.add_column_if_missing("transfer_tasks", "source_fingerprint", "TEXT")?;
+        self.add_column_if_missing("transfer_tasks", "version_id", "TEXT")?;
+        self.add_column_if_missing("transfer_tasks", "profile_id", "TEXT")?;
+        // Tasks from before profiles were recorded ran on the default one
+        self.conn.execute(
+            "UPDATE transfer_tasks SET profile_id =
+                (SELECT profile_id FROM connection_profiles WHERE is_default = 1 LIMIT 1)
+             WHERE profile_id IS NULL",
+            [],
+        )?;
+        
         self.conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_task_status ON transfer_tasks(status)",
             [],
         )?;
         
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_task_job ON transfer_tasks(job_id)",
+            [],
+        )?;
+        
+        // Create transfer_jobs table (folder transfers; progress comes from the child tasks)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS transfer_jobs (
+                job_id TEXT PRIMARY KEY,
+                job_type TEXT NOT NULL,
+                name TEXT NOT NULL,
+                local_path TEXT NOT NULL,
+                bucket_name TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        
         // Create encryption_keys table
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS encryption_keys (
@@ -72,51 +135,216 @@ impl Database {
             [],
         )?;
         
+        // Create app_settings table (JSON values keyed by setting name)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+        
+        // Create vault_meta table (salt, KDF parameters and passphrase check value)
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS vault_meta (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                salt TEXT NOT NULL,
+                kdf_params TEXT NOT NULL,
+                verifier TEXT NOT NULL,
+                idle_timeout_secs INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// Add a column to an existing table created by an older version
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+        
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Import the single-row connection_config table used by older versions
+    /// as the default profile, then drop it.
+    fn migrate_legacy_connection(&self) -> Result<()> {
+        let legacy_exists: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'connection_config'",
+            [],
+            |row| row.get(0),
+        )?;
+        
+        if legacy_exists == 0 {
+            return Ok(());
+        }
+        
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO connection_profiles
+             (profile_id, name, endpoint, port, access_key, secret_key, use_ssl, is_default, last_connected, created_at, updated_at)
+             SELECT ?1, 'Default', endpoint, port, access_key, secret_key, use_ssl, 1, last_connected, ?2, ?2
+             FROM connection_config WHERE id = 1",
+            params![uuid::Uuid::new_v4().to_string(), now],
+        )?;
+        tx.execute("DROP TABLE connection_config", [])?;
+        tx.commit()?;
+        
         Ok(())
     }
     
-    // Connection config methods
-    pub fn save_connection(&self, config: &ConnectionConfig) -> Result<()> {
+    // Connection profile methods
+    pub fn save_profile(&self, profile: &ConnectionProfile) -> Result<()> {
+        let tls_policy_json = serde_json::to_string(&profile.tls)
+            .map_err(|e| crate::error::AppError::Other(e.to_string()))?;
+        let secret_key = self.seal_secret(&profile.secret_key, &profile_secret_context(&profile.profile_id))?;
+        
         self.conn.execute(
-            "INSERT OR REPLACE INTO connection_config (id, endpoint, port, access_key, secret_key, use_ssl, last_connected)
-             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT OR REPLACE INTO connection_profiles
+             (profile_id, name, endpoint, port, access_key, secret_key, use_ssl, tls_policy, is_default, last_connected, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
-                config.endpoint,
-                config.port,
-                config.access_key,
-                config.secret_key,
-                config.use_ssl as i32,
-                config.last_connected.map(|dt| dt.to_rfc3339()),
+                profile.profile_id,
+                profile.name,
+                profile.endpoint,
+                profile.port,
+                profile.access_key,
+                secret_key,
+                profile.use_ssl as i32,
+                tls_policy_json,
+                profile.is_default as i32,
+                profile.last_connected.map(|dt| dt.to_rfc3339()),
+                profile.created_at.to_rfc3339(),
+                profile.updated_at.to_rfc3339(),
             ],
         )?;
         Ok(())
     }
     
-    pub fn get_connection(&self) -> Result<Option<ConnectionConfig>> {
+    pub fn get_profile(&self, profile_id: &str) -> Result<Option<ConnectionProfile>> {
         let mut stmt = self.conn.prepare(
-            "SELECT endpoint, port, access_key, secret_key, use_ssl, last_connected FROM connection_config WHERE id = 1"
-        )?;
-        
-        let result = stmt.query_row([], |row| {
-            Ok(ConnectionConfig {
-                endpoint: row.get(0)?,
-                port: row.get(1)?,
-                access_key: row.get(2)?,
-                secret_key: row.get(3)?,
-                use_ssl: row.get::<_, i32>(4)? != 0,
-                last_connected: row.get::<_, Option<String>>(5)?
-                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                    .map(|dt| dt.with_timezone(&Utc)),
-            })
-        });
+            "SELECT profile_id, name, endpoint, port, access_key, secret_key, use_ssl, is_default,
+                    last_connected, created_at, updated_at, tls_policy
+             FROM connection_profiles WHERE profile_id = ?1"
+        )?;
         
-        match result {
-            Ok(config) => Ok(Some(config)),
+        match stmt.query_row([profile_id], row_to_profile) {
+            Ok(profile) => Ok(Some(self.open_profile(profile)?)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
     
+    pub fn get_default_profile(&self) -> Result<Option<ConnectionProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT profile_id, name, endpoint, port, access_key, secret_key, use_ssl, is_default,
+                    last_connected, created_at, updated_at, tls_policy
+             FROM connection_profiles WHERE is_default = 1 LIMIT 1"
+        )?;
+        
+        match stmt.query_row([], row_to_profile) {
+            Ok(profile) => Ok(Some(self.open_profile(profile)?)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn list_profiles(&self) -> Result<Vec<ConnectionProfile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT profile_id, name, endpoint, port, access_key, secret_key, use_ssl, is_default,
+                    last_connected, created_at, updated_at, tls_policy
+             FROM connection_profiles ORDER BY name COLLATE NOCASE"
+        )?;
+        
+        let profiles = stmt.query_map([], row_to_profile)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        
+        // Listing works while the vault is locked; secrets are blanked instead
+        profiles.into_iter()
+            .map(|mut profile| {
+                if vault::is_sealed(&profile.secret_key) && !self.vault.is_unlocked() {
+                    profile.secret_key = String::new();
+                    Ok(profile)
+                } else {
+                    self.open_profile(profile)
+                }
+            })
+            .collect()
+    }
+    
+    pub fn profile_name_exists(&self, name: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM connection_profiles WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+    
+    pub fn rename_profile(&self, profile_id: &str, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE connection_profiles SET name = ?1, updated_at = ?2 WHERE profile_id = ?3",
+            params![name, Utc::now().to_rfc3339(), profile_id],
+        )?;
+        Ok(())
+    }
+    
+    /// Delete a profile. When it was the default, the most recently connected
+    /// remaining profile (or the first by name) becomes the default instead.
+    pub fn delete_profile(&self, profile_id: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let was_default: bool = tx.query_row(
+            "SELECT COUNT(*) FROM connection_profiles WHERE profile_id = ?1 AND is_default = 1",
+            [profile_id],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+        tx.execute("DELETE FROM connection_profiles WHERE profile_id = ?1", [profile_id])?;
+        if was_default {
+            tx.execute(
+                "UPDATE connection_profiles SET is_default = 1 WHERE profile_id = (
+                    SELECT profile_id FROM connection_profiles
+                    ORDER BY last_connected IS NULL, last_connected DESC, name COLLATE NOCASE
+                    LIMIT 1
+                )",
+                [],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    
+    pub fn set_default_profile(&self, profile_id: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE connection_profiles SET is_default = 0 WHERE is_default = 1", [])?;
+        tx.execute(
+            "UPDATE connection_profiles SET is_default = 1 WHERE profile_id = ?1",
+            [profile_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+    
+    pub fn touch_profile(&self, profile_id: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE connection_profiles SET last_connected = ?1 WHERE profile_id = ?2",
+            params![Utc::now().to_rfc3339(), profile_id],
+        )?;
+        Ok(())
+    }
+    
     // Transfer task methods
     pub fn save_task(&self, task: &TransferTask) -> Result<()> {
         let task_type = match task.task_type {
@@ -131,17 +359,33 @@ impl Database {
             TaskStatus::Completed => "completed",
             TaskStatus::Failed => "failed",
             TaskStatus::Cancelled => "cancelled",
+            TaskStatus::Corrupted => "corrupted",
+            TaskStatus::Interrupted => "interrupted",
+            TaskStatus::Skipped => "skipped",
         };
         
         let completed_parts_json = serde_json::to_string(&task.completed_parts)
             .map_err(|e| crate::error::AppError::Other(e.to_string()))?;
+        let source_fingerprint_json = task.source_fingerprint.as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| crate::error::AppError::Other(e.to_string()))?;
+        
+        let conflict_policy = match task.conflict_policy {
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::KeepBoth => "keep_both",
+            ConflictPolicy::OverwriteIfNewer => "overwrite_if_newer",
+        };
         
         self.conn.execute(
             "INSERT OR REPLACE INTO transfer_tasks 
              (task_id, task_type, file_name, local_path, bucket_name, object_key, file_size, 
               upload_id, part_size, total_parts, completed_parts, transferred_bytes, status, 
-              error_message, use_encryption, created_at, updated_at, completed_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+              error_message, use_encryption, created_at, updated_at, completed_at, priority, queue_position,
+              remote_etag, job_id, retry_count, bandwidth_limit, active_ms, encryption_key_md5, conflict_policy,
+              source_fingerprint, version_id, profile_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30)",
             params![
                 task.task_id,
                 task_type,
@@ -161,6 +405,18 @@ impl Database {
                 task.created_at.to_rfc3339(),
                 task.updated_at.to_rfc3339(),
                 task.completed_at.map(|dt| dt.to_rfc3339()),
+                task.priority,
+                task.queue_position,
+                task.remote_etag,
+                task.job_id,
+                task.retry_count,
+                task.bandwidth_limit,
+                task.active_ms,
+                task.encryption_key_md5,
+                conflict_policy,
+                source_fingerprint_json,
+                task.version_id,
+                task.profile_id,
             ],
         )?;
         
@@ -168,57 +424,11 @@ impl Database {
     }
     
     pub fn get_task(&self, task_id: &str) -> Result<Option<TransferTask>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT task_id, task_type, file_name, local_path, bucket_name, object_key, file_size,
-                    upload_id, part_size, total_parts, completed_parts, transferred_bytes, status,
-                    error_message, use_encryption, created_at, updated_at, completed_at
-             FROM transfer_tasks WHERE task_id = ?1"
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks WHERE task_id = ?1", TASK_COLUMNS
+        ))?;
         
-        let result = stmt.query_row([task_id], |row| {
-            let completed_parts_json: String = row.get(10)?;
-            let completed_parts: Vec<CompletedPart> = serde_json::from_str(&completed_parts_json)
-                .unwrap_or_default();
-            
-            Ok(TransferTask {
-                task_id: row.get(0)?,
-                task_type: match row.get::<_, String>(1)?.as_str() {
-                    "upload" => TaskType::Upload,
-                    _ => TaskType::Download,
-                },
-                file_name: row.get(2)?,
-                local_path: row.get(3)?,
-                bucket_name: row.get(4)?,
-                object_key: row.get(5)?,
-                file_size: row.get(6)?,
-                upload_id: row.get(7)?,
-                part_size: row.get(8)?,
-                total_parts: row.get(9)?,
-                completed_parts,
-                transferred_bytes: row.get(11)?,
-                status: match row.get::<_, String>(12)?.as_str() {
-                    "running" => TaskStatus::Running,
-                    "paused" => TaskStatus::Paused,
-                    "completed" => TaskStatus::Completed,
-                    "failed" => TaskStatus::Failed,
-                    "cancelled" => TaskStatus::Cancelled,
-                    _ => TaskStatus::Pending,
-                },
-                error_message: row.get(13)?,
-                use_encryption: row.get::<_, i32>(14)? != 0,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(15)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(16)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                completed_at: row.get::<_, Option<String>>(17)?
-                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                    .map(|dt| dt.with_timezone(&Utc)),
-            })
-        });
-        
-        match result {
+        match stmt.query_row([task_id], row_to_task) {
             Ok(task) => Ok(Some(task)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
@@ -226,60 +436,107 @@ impl Database {
     }
     
     pub fn get_all_tasks(&self) -> Result<Vec<TransferTask>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT task_id, task_type, file_name, local_path, bucket_name, object_key, file_size,
-                    upload_id, part_size, total_parts, completed_parts, transferred_bytes, status,
-                    error_message, use_encryption, created_at, updated_at, completed_at
-             FROM transfer_tasks ORDER BY created_at DESC"
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks ORDER BY created_at DESC", TASK_COLUMNS
+        ))?;
         
-        let tasks = stmt.query_map([], |row| {
-            let completed_parts_json: String = row.get(10)?;
-            let completed_parts: Vec<CompletedPart> = serde_json::from_str(&completed_parts_json)
-                .unwrap_or_default();
-            
-            Ok(TransferTask {
-                task_id: row.get(0)?,
-                task_type: match row.get::<_, String>(1)?.as_str() {
-                    "upload" => TaskType::Upload,
-                    _ => TaskType::Download,
-                },
-                file_name: row.get(2)?,
-                local_path: row.get(3)?,
-                bucket_name: row.get(4)?,
-                object_key: row.get(5)?,
-                file_size: row.get(6)?,
-                upload_id: row.get(7)?,
-                part_size: row.get(8)?,
-                total_parts: row.get(9)?,
-                completed_parts,
-                transferred_bytes: row.get(11)?,
-                status: match row.get::<_, String>(12)?.as_str() {
-                    "running" => TaskStatus::Running,
-                    "paused" => TaskStatus::Paused,
-                    "completed" => TaskStatus::Completed,
-                    "failed" => TaskStatus::Failed,
-                    "cancelled" => TaskStatus::Cancelled,
-                    _ => TaskStatus::Pending,
-                },
-                error_message: row.get(13)?,
-                use_encryption: row.get::<_, i32>(14)? != 0,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(15)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(16)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                completed_at: row.get::<_, Option<String>>(17)?
-                    .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
-                    .map(|dt| dt.with_timezone(&Utc)),
-            })
-        })?;
+        let tasks = stmt.query_map([], row_to_task)?;
+        tasks.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
+    
+    /// The task that started a multipart upload, if it still exists
+    pub fn get_task_by_upload_id(&self, upload_id: &str) -> Result<Option<TransferTask>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks WHERE upload_id = ?1 LIMIT 1", TASK_COLUMNS
+        ))?;
+        
+        match stmt.query_row([upload_id], row_to_task) {
+            Ok(task) => Ok(Some(task)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Pending tasks in the order the scheduler starts them
+    pub fn get_pending_tasks(&self) -> Result<Vec<TransferTask>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks WHERE status = 'pending'
+             ORDER BY priority DESC, queue_position ASC, created_at ASC", TASK_COLUMNS
+        ))?;
+        
+        let tasks = stmt.query_map([], row_to_task)?;
+        tasks.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
+    
+    /// Tasks cut off by the app quitting, which still need reconciling
+    pub fn get_interrupted_tasks(&self) -> Result<Vec<TransferTask>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks WHERE status = 'interrupted'
+             ORDER BY queue_position ASC", TASK_COLUMNS
+        ))?;
         
+        let tasks = stmt.query_map([], row_to_task)?;
         tasks.collect::<std::result::Result<Vec<_>, _>>()
             .map_err(|e| e.into())
     }
     
+    /// Mark tasks left `running` by a previous session as interrupted.
+    /// Only call this at startup, before any task can be running.
+    pub fn mark_running_tasks_interrupted(&self) -> Result<usize> {
+        let count = self.conn.execute(
+            "UPDATE transfer_tasks SET status = 'interrupted', updated_at = ?1 WHERE status = 'running'",
+            [Utc::now().to_rfc3339()],
+        )?;
+        Ok(count)
+    }
+    
+    /// Position for a task appended to the end of the queue
+    pub fn next_queue_position(&self) -> Result<i64> {
+        let position: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(queue_position), 0) + 1 FROM transfer_tasks",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(position)
+    }
+    
+    pub fn set_task_priority(&self, task_id: &str, priority: i32) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE transfer_tasks SET priority = ?1, updated_at = ?2 WHERE task_id = ?3",
+            params![priority, Utc::now().to_rfc3339(), task_id],
+        )?;
+        if updated == 0 {
+            return Err(crate::error::AppError::TaskNotFound(task_id.to_string()));
+        }
+        Ok(())
+    }
+    
+    pub fn set_task_bandwidth_limit(&self, task_id: &str, limit: Option<i64>) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE transfer_tasks SET bandwidth_limit = ?1, updated_at = ?2 WHERE task_id = ?3",
+            params![limit, Utc::now().to_rfc3339(), task_id],
+        )?;
+        if updated == 0 {
+            return Err(crate::error::AppError::TaskNotFound(task_id.to_string()));
+        }
+        Ok(())
+    }
+    
+    /// Renumber queue positions to follow the given task order
+    pub fn reorder_tasks(&self, task_ids: &[String]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (position, task_id) in task_ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE transfer_tasks SET queue_position = ?1 WHERE task_id = ?2",
+                params![position as i64 + 1, task_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+    
     pub fn delete_task(&self, task_id: &str) -> Result<()> {
         self.conn.execute("DELETE FROM transfer_tasks WHERE task_id = ?1", [task_id])?;
         Ok(())
@@ -293,6 +550,9 @@ impl Database {
             crate::models::TaskStatus::Completed => "completed",
             crate::models::TaskStatus::Failed => "failed",
             crate::models::TaskStatus::Cancelled => "cancelled",
+            crate::models::TaskStatus::Corrupted => "corrupted",
+            crate::models::TaskStatus::Interrupted => "interrupted",
+            crate::models::TaskStatus::Skipped => "skipped",
         };
         
         self.conn.execute(
@@ -303,14 +563,123 @@ impl Database {
         Ok(())
     }
 
+    // Transfer job methods
+    pub fn save_job(&self, job: &TransferJob) -> Result<()> {
+        let job_type = match job.job_type {
+            TaskType::Upload => "upload",
+            TaskType::Download => "download",
+        };
+        
+        self.conn.execute(
+            "INSERT OR REPLACE INTO transfer_jobs
+             (job_id, job_type, name, local_path, bucket_name, prefix, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                job.job_id,
+                job_type,
+                job.name,
+                job.local_path,
+                job.bucket_name,
+                job.prefix,
+                job.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+    
+    /// Jobs with progress aggregated from their child tasks
+    pub fn get_jobs(&self) -> Result<Vec<TransferJob>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT j.job_id, j.job_type, j.name, j.local_path, j.bucket_name, j.prefix, j.created_at,
+                    COUNT(t.task_id),
+                    SUM(t.status IN ('completed', 'skipped')), SUM(t.status IN ('failed', 'corrupted')),
+                    SUM(t.file_size), SUM(t.transferred_bytes),
+                    SUM(t.status = 'running'), SUM(t.status = 'pending'),
+                    SUM(t.status IN ('paused', 'interrupted')), SUM(t.status = 'cancelled')
+             FROM transfer_jobs j
+             JOIN transfer_tasks t ON t.job_id = j.job_id
+             GROUP BY j.job_id
+             ORDER BY j.created_at DESC"
+        )?;
+        
+        let jobs = stmt.query_map([], |row| {
+            let file_count: i64 = row.get(7)?;
+            let completed_count: i64 = row.get(8)?;
+            let failed_count: i64 = row.get(9)?;
+            let running: i64 = row.get(12)?;
+            let pending: i64 = row.get(13)?;
+            let paused: i64 = row.get(14)?;
+            let cancelled: i64 = row.get(15)?;
+            
+            // The job is as active as its most active child
+            let status = if running > 0 {
+                TaskStatus::Running
+            } else if pending > 0 {
+                TaskStatus::Pending
+            } else if paused > 0 {
+                TaskStatus::Paused
+            } else if failed_count > 0 {
+                TaskStatus::Failed
+            } else if cancelled == file_count {
+                TaskStatus::Cancelled
+            } else {
+                TaskStatus::Completed
+            };
+            
+            Ok(TransferJob {
+                job_id: row.get(0)?,
+                job_type: match row.get::<_, String>(1)?.as_str() {
+                    "upload" => TaskType::Upload,
+                    _ => TaskType::Download,
+                },
+                name: row.get(2)?,
+                local_path: row.get(3)?,
+                bucket_name: row.get(4)?,
+                prefix: row.get(5)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+                    .unwrap()
+                    .with_timezone(&Utc),
+                file_count,
+                completed_count,
+                failed_count,
+                total_bytes: row.get(10)?,
+                transferred_bytes: row.get(11)?,
+                status,
+            })
+        })?;
+        
+        jobs.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
+    
+    pub fn get_job_tasks(&self, job_id: &str) -> Result<Vec<TransferTask>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM transfer_tasks WHERE job_id = ?1 ORDER BY queue_position ASC", TASK_COLUMNS
+        ))?;
+        
+        let tasks = stmt.query_map([job_id], row_to_task)?;
+        tasks.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| e.into())
+    }
+    
+    /// Delete a job together with its child tasks
+    pub fn delete_job(&self, job_id: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM transfer_tasks WHERE job_id = ?1", [job_id])?;
+        tx.execute("DELETE FROM transfer_jobs WHERE job_id = ?1", [job_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+    
     // Encryption key methods
     pub fn save_encryption_key(&self, key: &EncryptionKey) -> Result<()> {
+        let key_value = self.seal_secret(&key.key_value, &encryption_key_context(&key.key_id))?;
         self.conn.execute(
             "INSERT OR REPLACE INTO encryption_keys (key_id, key_value, key_md5, enabled, created_at)
              VALUES (?1, ?2, ?3, ?4, ?5)",
             params![
                 key.key_id,
-                key.key_value,
+                key_value,
                 key.key_md5,
                 key.enabled as i32,
                 key.created_at.to_rfc3339(),
@@ -325,20 +694,27 @@ impl Database {
              FROM encryption_keys ORDER BY created_at DESC LIMIT 1"
         )?;
         
-        let result = stmt.query_row([], |row| {
-            Ok(EncryptionKey {
-                key_id: row.get(0)?,
-                key_value: row.get(1)?,
-                key_md5: row.get(2)?,
-                enabled: row.get::<_, i32>(3)? != 0,
-                created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        });
+        let result = stmt.query_row([], row_to_encryption_key);
+        self.open_encryption_key(result)
+    }
+    
+    /// Look up a key by the MD5 sent in SSE-C headers, newest or not
+    pub fn get_encryption_key_by_md5(&self, key_md5: &str) -> Result<Option<EncryptionKey>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT key_id, key_value, key_md5, enabled, created_at 
+             FROM encryption_keys WHERE key_md5 = ?1 ORDER BY created_at DESC LIMIT 1"
+        )?;
         
+        let result = stmt.query_row([key_md5], row_to_encryption_key);
+        self.open_encryption_key(result)
+    }
+    
+    fn open_encryption_key(&self, result: rusqlite::Result<EncryptionKey>) -> Result<Option<EncryptionKey>> {
         match result {
-            Ok(key) => Ok(Some(key)),
+            Ok(mut key) => {
+                key.key_value = self.vault.open(&key.key_value, &encryption_key_context(&key.key_id))?;
+                Ok(Some(key))
+            }
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(e.into()),
         }
@@ -352,4 +728,377 @@ impl Database {
         )?;
         Ok(())
     }
+    
+    // Settings methods
+    pub fn get_transfer_settings(&self) -> Result<TransferSettings> {
+        let result = self.conn.query_row(
+            "SELECT value FROM app_settings WHERE key = 'transfer'",
+            [],
+            |row| row.get::<_, String>(0),
+        );
+        
+        match result {
+            Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_default()),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(TransferSettings::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn save_transfer_settings(&self, settings: &TransferSettings) -> Result<()> {
+        let json = serde_json::to_string(settings)
+            .map_err(|e| crate::error::AppError::Other(e.to_string()))?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('transfer', ?1)",
+            [json],
+        )?;
+        Ok(())
+    }
+    
+    // Vault methods
+    fn load_vault_settings(&self) -> Result<()> {
+        let result = self.conn.query_row(
+            "SELECT idle_timeout_secs FROM vault_meta WHERE id = 1",
+            [],
+            |row| row.get::<_, i64>(0),
+        );
+        
+        match result {
+            Ok(idle_timeout_secs) => {
+                self.vault.set_configured(true);
+                self.vault.set_idle_timeout(std::time::Duration::from_secs(idle_timeout_secs.max(0) as u64));
+                Ok(())
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    pub fn vault_status(&self) -> VaultStatus {
+        VaultStatus {
+            configured: self.vault.is_configured(),
+            unlocked: self.vault.is_unlocked(),
+            idle_timeout_secs: self.vault.idle_timeout().as_secs(),
+        }
+    }
+    
+    /// Salt and KDF parameters needed to derive the vault key
+    pub fn vault_kdf_params(&self) -> Result<Option<(Vec<u8>, KdfParams)>> {
+        let result = self.conn.query_row(
+            "SELECT salt, kdf_params FROM vault_meta WHERE id = 1",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        );
+        
+        match result {
+            Ok((salt, params)) => {
+                let salt = general_purpose::STANDARD.decode(salt)
+                    .map_err(|e| AppError::Encryption(format!("Corrupted vault salt: {}", e)))?;
+                let params = serde_json::from_str(&params)
+                    .map_err(|e| AppError::Encryption(format!("Corrupted vault parameters: {}", e)))?;
+                Ok(Some((salt, params)))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Create the vault with a key derived from a new passphrase and
+    /// encrypt all existing plaintext secrets.
+    pub fn setup_vault(&self, key: Zeroizing<[u8; 32]>, salt: &[u8], params: &KdfParams) -> Result<usize> {
+        if self.vault.is_configured() {
+            return Err(AppError::InvalidConfig("Vault is already set up".to_string()));
+        }
+        
+        self.vault.unlock(key);
+        let verifier = self.vault.seal(VAULT_VERIFIER_CONTEXT, VAULT_VERIFIER_CONTEXT)?;
+        let params_json = serde_json::to_string(params)
+            .map_err(|e| AppError::Other(e.to_string()))?;
+        
+        self.conn.execute(
+            "INSERT INTO vault_meta (id, salt, kdf_params, verifier, idle_timeout_secs, created_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5)",
+            params![
+                general_purpose::STANDARD.encode(salt),
+                params_json,
+                verifier,
+                self.vault.idle_timeout().as_secs() as i64,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        self.vault.set_configured(true);
+        
+        self.encrypt_plaintext_secrets()
+    }
+    
+    /// Unlock the vault, rejecting a key derived from the wrong passphrase
+    pub fn unlock_vault(&self, key: Zeroizing<[u8; 32]>) -> Result<usize> {
+        let verifier: String = self.conn.query_row(
+            "SELECT verifier FROM vault_meta WHERE id = 1",
+            [],
+            |row| row.get(0),
+        ).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::InvalidConfig("Vault is not set up".to_string()),
+            e => e.into(),
+        })?;
+        
+        self.vault.unlock(key);
+        if self.vault.open(&verifier, VAULT_VERIFIER_CONTEXT).is_err() {
+            self.vault.lock();
+            return Err(AppError::Encryption("Incorrect passphrase".to_string()));
+        }
+        
+        // Catch rows written in plaintext since the vault was created (e.g. legacy imports)
+        self.encrypt_plaintext_secrets()
+    }
+    
+    pub fn lock_vault(&self) {
+        self.vault.lock();
+    }
+    
+    pub fn lock_vault_if_idle(&self) -> bool {
+        self.vault.lock_if_idle()
+    }
+    
+    pub fn set_vault_idle_timeout(&self, seconds: u64) -> Result<()> {
+        self.vault.set_idle_timeout(std::time::Duration::from_secs(seconds));
+        self.conn.execute(
+            "UPDATE vault_meta SET idle_timeout_secs = ?1 WHERE id = 1",
+            params![seconds as i64],
+        )?;
+        Ok(())
+    }
+    
+    /// Migration: encrypt secret columns that are still stored in plaintext.
+    /// Requires an unlocked vault. Returns the number of rows rewritten.
+    fn encrypt_plaintext_secrets(&self) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut rewritten = 0;
+        
+        let profiles: Vec<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT profile_id, secret_key FROM connection_profiles")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (profile_id, secret_key) in profiles {
+            if !vault::is_sealed(&secret_key) {
+                let sealed = self.vault.seal(&secret_key, &profile_secret_context(&profile_id))?;
+                tx.execute(
+                    "UPDATE connection_profiles SET secret_key = ?1 WHERE profile_id = ?2",
+                    params![sealed, profile_id],
+                )?;
+                rewritten += 1;
+            }
+        }
+        
+        let keys: Vec<(String, String)> = {
+            let mut stmt = tx.prepare("SELECT key_id, key_value FROM encryption_keys")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<std::result::Result<Vec<_>, _>>()?
+        };
+        for (key_id, key_value) in keys {
+            if !vault::is_sealed(&key_value) {
+                let sealed = self.vault.seal(&key_value, &encryption_key_context(&key_id))?;
+                tx.execute(
+                    "UPDATE encryption_keys SET key_value = ?1 WHERE key_id = ?2",
+                    params![sealed, key_id],
+                )?;
+                rewritten += 1;
+            }
+        }
+        
+        tx.commit()?;
+        if rewritten > 0 {
+            println!("[Vault] Encrypted {} plaintext secrets", rewritten);
+        }
+        Ok(rewritten)
+    }
+    
+    /// Seal a secret if the vault is set up; without a vault secrets stay plaintext
+    fn seal_secret(&self, value: &str, context: &str) -> Result<String> {
+        if self.vault.is_configured() {
+            self.vault.seal(value, context)
+        } else {
+            Ok(value.to_string())
+        }
+    }
+    
+    fn open_profile(&self, mut profile: ConnectionProfile) -> Result<ConnectionProfile> {
+        profile.secret_key = self.vault.open(&profile.secret_key, &profile_secret_context(&profile.profile_id))?;
+        Ok(profile)
+    }
+}
+
+fn row_to_profile(row: &rusqlite::Row) -> rusqlite::Result<ConnectionProfile> {
+    Ok(ConnectionProfile {
+        profile_id: row.get(0)?,
+        name: row.get(1)?,
+        endpoint: row.get(2)?,
+        port: row.get(3)?,
+        access_key: row.get(4)?,
+        secret_key: row.get(5)?,
+        use_ssl: row.get::<_, i32>(6)? != 0,
+        tls: row.get::<_, Option<String>>(11)?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+        is_default: row.get::<_, i32>(7)? != 0,
+        last_connected: row.get::<_, Option<String>>(8)?
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
+
+fn row_to_encryption_key(row: &rusqlite::Row) -> rusqlite::Result<EncryptionKey> {
+    Ok(EncryptionKey {
+        key_id: row.get(0)?,
+        key_value: row.get(1)?,
+        key_md5: row.get(2)?,
+        enabled: row.get::<_, i32>(3)? != 0,
+        created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
+
+fn row_to_task(row: &rusqlite::Row) -> rusqlite::Result<TransferTask> {
+    let completed_parts_json: String = row.get(10)?;
+    let completed_parts: Vec<CompletedPart> = serde_json::from_str(&completed_parts_json)
+        .unwrap_or_default();
+    let transferred_bytes: i64 = row.get(11)?;
+    let active_ms: i64 = row.get(24)?;
+    
+    Ok(TransferTask {
+        task_id: row.get(0)?,
+        task_type: match row.get::<_, String>(1)?.as_str() {
+            "upload" => TaskType::Upload,
+            _ => TaskType::Download,
+        },
+        file_name: row.get(2)?,
+        local_path: row.get(3)?,
+        bucket_name: row.get(4)?,
+        object_key: row.get(5)?,
+        file_size: row.get(6)?,
+        upload_id: row.get(7)?,
+        part_size: row.get(8)?,
+        total_parts: row.get(9)?,
+        completed_parts,
+        transferred_bytes,
+        status: match row.get::<_, String>(12)?.as_str() {
+            "running" => TaskStatus::Running,
+            "paused" => TaskStatus::Paused,
+            "completed" => TaskStatus::Completed,
+            "failed" => TaskStatus::Failed,
+            "cancelled" => TaskStatus::Cancelled,
+            "corrupted" => TaskStatus::Corrupted,
+            "interrupted" => TaskStatus::Interrupted,
+            "skipped" => TaskStatus::Skipped,
+            _ => TaskStatus::Pending,
+        },
+        error_message: row.get(13)?,
+        use_encryption: row.get::<_, i32>(14)? != 0,
+        created_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(15)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(16)?)
+            .unwrap()
+            .with_timezone(&Utc),
+        completed_at: row.get::<_, Option<String>>(17)?
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
+        priority: row.get(18)?,
+        queue_position: row.get(19)?,
+        remote_etag: row.get(20)?,
+        job_id: row.get(21)?,
+        retry_count: row.get(22)?,
+        bandwidth_limit: row.get(23)?,
+        encryption_key_md5: row.get(25)?,
+        conflict_policy: match row.get::<_, String>(26)?.as_str() {
+            "skip" => ConflictPolicy::Skip,
+            "keep_both" => ConflictPolicy::KeepBoth,
+            "overwrite_if_newer" => ConflictPolicy::OverwriteIfNewer,
+            _ => ConflictPolicy::Overwrite,
+        },
+        source_fingerprint: row.get::<_, Option<String>>(27)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        version_id: row.get(28)?,
+        profile_id: row.get(29)?,
+        active_ms,
+        average_speed: crate::stats::average_speed(transferred_bytes, active_ms),
+        speed: 0.0,
+        eta_seconds: None,
+    })
+}
+
+fn profile_secret_context(profile_id: &str) -> String {
+    format!("connection_profiles.secret_key:{}", profile_id)
+}
+
+fn encryption_key_context(key_id: &str) -> String {
+    format!("encryption_keys.key_value:{}", key_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TlsPolicy;
+
+    fn profile(db: &Database, name: &str, is_default: bool, last_connected: Option<i64>) -> String {
+        let now = Utc::now();
+        let profile = ConnectionProfile {
+            profile_id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            endpoint: "localhost".to_string(),
+            port: 9000,
+            access_key: "access".to_string(),
+            secret_key: "secret".to_string(),
+            use_ssl: false,
+            tls: TlsPolicy::default(),
+            is_default,
+            last_connected: last_connected.and_then(|secs| chrono::DateTime::from_timestamp(secs, 0)),
+            created_at: now,
+            updated_at: now,
+        };
+        db.save_profile(&profile).unwrap();
+        profile.profile_id
+    }
+
+    fn default_name(db: &Database) -> Option<String> {
+        db.get_default_profile().unwrap().map(|p| p.name)
+    }
+
+    #[test]
+    fn test_delete_default_profile_promotes_another() {
+        let db = Database::new(":memory:").unwrap();
+        let home = profile(&db, "home", true, Some(100));
+        let work = profile(&db, "work", false, Some(200));
+        profile(&db, "archive", false, None);
+
+        // Deleting a profile that isn't the default leaves the default alone
+        db.delete_profile(&work).unwrap();
+        assert_eq!(default_name(&db).as_deref(), Some("home"));
+
+        // Without a recent connection, the first remaining name wins
+        db.delete_profile(&home).unwrap();
+        assert_eq!(default_name(&db).as_deref(), Some("archive"));
+
+        let archive = db.get_default_profile().unwrap().unwrap().profile_id;
+        db.delete_profile(&archive).unwrap();
+        assert_eq!(default_name(&db), None);
+    }
+
+    #[test]
+    fn test_delete_default_profile_prefers_recent_connection() {
+        let db = Database::new(":memory:").unwrap();
+        let home = profile(&db, "home", true, Some(100));
+        profile(&db, "alpha", false, None);
+        profile(&db, "work", false, Some(200));
+
+        db.delete_profile(&home).unwrap();
+        assert_eq!(default_name(&db).as_deref(), Some("work"));
+    }
 }
@@ -25,6 +25,13 @@ impl SseCEncryption {
         Ok(Self { key, key_md5 })
     }
 
+    /// Create SSE-C encryption from a base64-encoded key
+    pub fn from_base64(key_base64: &str) -> Result<Self> {
+        let key = general_purpose::STANDARD.decode(key_base64)
+            .map_err(|e| AppError::Encryption(e.to_string()))?;
+        Self::new(key)
+    }
+
     /// Get base64-encoded key for S3 headers
     pub fn get_key_base64(&self) -> String {
         general_purpose::STANDARD.encode(&self.key)
@@ -11,22 +11,49 @@ pub enum AppError {
     #[error("S3 error: {0}")]
     S3(String),
     
+    /// A failure that may succeed on retry (timeouts, throttling, 5xx)
+    #[error("Temporary failure: {0}")]
+    Transient(String),
+    
     #[error("Connection not configured")]
     NotConnected,
     
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
     
+    #[error("Connection profile not found: {0}")]
+    ProfileNotFound(String),
+    
     #[error("Task not found: {0}")]
     TaskNotFound(String),
     
+    #[error("Task stopped")]
+    TaskStopped,
+    
+    /// The conflict policy decided not to transfer
+    #[error("Skipped: {0}")]
+    Skipped(String),
+    
+    /// Transferred data didn't match the checksum or ETag it was checked against
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+    
     #[error("Encryption error: {0}")]
     Encryption(String),
     
+    #[error("Vault is locked")]
+    VaultLocked,
+    
     #[error("{0}")]
     Other(String),
 }
 
+impl AppError {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AppError::Transient(_))
+    }
+}
+
 impl serde::Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
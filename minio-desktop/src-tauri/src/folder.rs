@@ -0,0 +1,167 @@
+// Directory walking and object key mapping for folder transfers.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use crate::error::{AppError, Result};
+
+/// Collect every regular file below `root`. Symlinks are skipped unless
+/// `follow_symlinks` is set; followed directories are visited once, so
+/// link cycles terminate.
+pub fn collect_files(root: &Path, follow_symlinks: bool) -> Result<Vec<PathBuf>> {
+    if !root.is_dir() {
+        return Err(AppError::InvalidConfig(format!("{} is not a directory", root.display())));
+    }
+
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    visited.insert(root.canonicalize()?);
+
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let mut entries = std::fs::read_dir(&dir)?
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+
+            if file_type.is_symlink() {
+                if !follow_symlinks {
+                    continue;
+                }
+                // Broken links are skipped rather than failing the whole walk
+                let Ok(metadata) = std::fs::metadata(&path) else {
+                    eprintln!("[Folder] Skipping broken symlink {}", path.display());
+                    continue;
+                };
+                if metadata.is_dir() {
+                    if visited.insert(path.canonicalize()?) {
+                        stack.push(path);
+                    }
+                } else if metadata.is_file() {
+                    files.push(path);
+                }
+            } else if file_type.is_dir() {
+                if visited.insert(path.canonicalize()?) {
+                    stack.push(path);
+                }
+            } else if file_type.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Object key for `file` under `prefix`, using its path relative to `root`
+/// with `/` separators regardless of platform
+pub fn object_key_for(root: &Path, file: &Path, prefix: &str) -> Result<String> {
+    let relative = file.strip_prefix(root)
+        .map_err(|_| AppError::Other(format!("{} is not inside {}", file.display(), root.display())))?;
+
+    let mut parts = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().replace('\\', "/")),
+            _ => return Err(AppError::Other(format!("Unexpected path component in {}", relative.display()))),
+        }
+    }
+
+    Ok(format!("{}{}", normalize_prefix(prefix), parts.join("/")))
+}
+
+/// Local path for the object `key` listed under `prefix`, below `root`.
+/// Returns `None` for keys that cannot name a file (directory markers) and
+/// an error for keys that would escape `root`. Every segment must be a
+/// single plain path component: `..`, roots and Windows drive prefixes such
+/// as `C:evil` would otherwise replace or leave `root`.
+pub fn local_path_for(root: &Path, key: &str, prefix: &str) -> Result<Option<PathBuf>> {
+    let relative = key.strip_prefix(prefix).unwrap_or(key);
+    if relative.ends_with('/') {
+        return Ok(None);
+    }
+
+    let mut path = root.to_path_buf();
+    let mut segments = 0;
+    for segment in relative.split('/').filter(|s| !s.is_empty()) {
+        let mut components = Path::new(segment).components();
+        let plain = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+        if !plain || segment.contains('\\') {
+            return Err(AppError::Other(format!("Object key {} cannot be mapped to a local path", key)));
+        }
+        path.push(segment);
+        segments += 1;
+    }
+
+    Ok(if segments == 0 { None } else { Some(path) })
+}
+
+/// Use `/` separators, drop leading and duplicate slashes and make a
+/// non-empty prefix end with `/`
+pub fn normalize_prefix(prefix: &str) -> String {
+    let segments: Vec<&str> = prefix
+        .split(['/', '\\'])
+        .filter(|s| !s.is_empty())
+        .collect();
+    if segments.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", segments.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_prefix() {
+        assert_eq!(normalize_prefix(""), "");
+        assert_eq!(normalize_prefix("/"), "");
+        assert_eq!(normalize_prefix("photos"), "photos/");
+        assert_eq!(normalize_prefix("/photos//2024\\trip/"), "photos/2024/trip/");
+    }
+
+    #[test]
+    fn test_object_key_for() {
+        let root = Path::new("base");
+        let file = root.join("a").join("b.txt");
+        assert_eq!(object_key_for(root, &file, "backup").unwrap(), "backup/a/b.txt");
+        assert_eq!(object_key_for(root, &file, "").unwrap(), "a/b.txt");
+        assert!(object_key_for(root, Path::new("other/b.txt"), "").is_err());
+    }
+
+    #[test]
+    fn test_local_path_for() {
+        let root = Path::new("dest");
+        assert_eq!(
+            local_path_for(root, "photos/2024/a.jpg", "photos/").unwrap(),
+            Some(root.join("2024").join("a.jpg"))
+        );
+        assert_eq!(local_path_for(root, "photos/2024/", "photos/").unwrap(), None);
+        assert!(local_path_for(root, "photos/../../etc/passwd", "photos/").is_err());
+        assert!(local_path_for(root, "photos/./a.jpg", "photos/").is_err());
+        assert!(local_path_for(root, "photos/a\\..\\b", "photos/").is_err());
+        #[cfg(windows)]
+        assert!(local_path_for(root, "photos/C:evil/a.jpg", "photos/").is_err());
+    }
+
+    #[test]
+    fn test_collect_files() {
+        let root = std::env::temp_dir().join(format!("folder-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        std::fs::write(root.join("top.txt"), b"1").unwrap();
+        std::fs::write(root.join("sub/deeper/leaf.txt"), b"2").unwrap();
+
+        let files = collect_files(&root, false).unwrap();
+        let keys: Vec<String> = files.iter()
+            .map(|f| object_key_for(&root, f, "").unwrap())
+            .collect();
+        assert_eq!(keys, vec!["sub/deeper/leaf.txt", "top.txt"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+// Integrity checks for transfers based on S3 ETags. For unencrypted objects
+// a single-PUT ETag is the MD5 of the content and a multipart ETag is the
+// MD5 of the concatenated part MD5s followed by `-<part count>`.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use base64::{Engine as _, engine::general_purpose};
+use md5::{Md5, Digest};
+use crate::error::Result;
+use crate::models::SourceFingerprint;
+
+/// Bytes hashed at each sample point of a source fingerprint
+const FINGERPRINT_SAMPLE: u64 = 64 * 1024;
+
+pub type Md5Digest = [u8; 16];
+
+pub fn md5(data: &[u8]) -> Md5Digest {
+    Md5::digest(data).into()
+}
+
+/// Value of the `Content-MD5` header for a digest
+pub fn content_md5(digest: &Md5Digest) -> String {
+    general_purpose::STANDARD.encode(digest)
+}
+
+pub fn to_hex(digest: &Md5Digest) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Option<Md5Digest> {
+    if hex.len() != 32 || !hex.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 16];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// ETag of a multipart upload built from these parts, in part order
+pub fn composite_etag(part_digests: &[Md5Digest]) -> String {
+    let mut hasher = Md5::new();
+    for digest in part_digests {
+        hasher.update(digest);
+    }
+    format!("{}-{}", to_hex(&hasher.finalize().into()), part_digests.len())
+}
+
+/// How an ETag can be checked against local data
+#[derive(Debug, PartialEq)]
+pub enum EtagKind {
+    /// MD5 of the whole object
+    Simple(String),
+    /// Multipart ETag over this many parts
+    Multipart(String, usize),
+    /// Not derived from the content (e.g. encrypted objects); can't be checked
+    Opaque,
+}
+
+/// Strip quotes and work out what the ETag was computed from
+pub fn parse_etag(etag: &str) -> EtagKind {
+    let etag = etag.trim().trim_matches('"').to_ascii_lowercase();
+    match etag.split_once('-') {
+        None if from_hex(&etag).is_some() => EtagKind::Simple(etag),
+        Some((hash, count)) if from_hex(hash).is_some() => match count.parse::<usize>() {
+            Ok(parts) if parts > 0 => EtagKind::Multipart(etag.clone(), parts),
+            _ => EtagKind::Opaque,
+        },
+        _ => EtagKind::Opaque,
+    }
+}
+
+/// Compare two ETags ignoring quotes and case
+pub fn etags_match(a: &str, b: &str) -> bool {
+    a.trim().trim_matches('"').eq_ignore_ascii_case(b.trim().trim_matches('"'))
+}
+
+/// Compute the ETag a file would have if uploaded in parts of `part_size`
+/// bytes (or in a single PUT when `part_size` is `None`). Blocking.
+pub fn file_etag(path: &Path, part_size: Option<u64>) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    let Some(part_size) = part_size.filter(|s| *s > 0) else {
+        let mut hasher = Md5::new();
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+        return Ok(to_hex(&hasher.finalize().into()));
+    };
+
+    let mut digests = Vec::new();
+    loop {
+        let mut hasher = Md5::new();
+        let mut in_part = 0u64;
+        while in_part < part_size {
+            let want = buffer.len().min((part_size - in_part) as usize);
+            let n = file.read(&mut buffer[..want])?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+            in_part += n as u64;
+        }
+        if in_part == 0 {
+            break;
+        }
+        digests.push(hasher.finalize().into());
+        if in_part < part_size {
+            break;
+        }
+    }
+    Ok(composite_etag(&digests))
+}
+
+/// Fingerprint a file by size, modification time and a hash of samples
+/// from its start, middle and end. Small files are hashed whole. Blocking.
+pub fn fingerprint(path: &Path) -> Result<SourceFingerprint> {
+    let mut file = std::fs::File::open(path)?;
+    let metadata = file.metadata()?;
+    let size = metadata.len();
+    let modified_ms = metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64);
+
+    let offsets = if size <= 3 * FINGERPRINT_SAMPLE {
+        vec![(0, size)]
+    } else {
+        vec![
+            (0, FINGERPRINT_SAMPLE),
+            (size / 2 - FINGERPRINT_SAMPLE / 2, FINGERPRINT_SAMPLE),
+            (size - FINGERPRINT_SAMPLE, FINGERPRINT_SAMPLE),
+        ]
+    };
+
+    let mut hasher = Md5::new();
+    let mut buffer = Vec::new();
+    for (offset, len) in offsets {
+        buffer.resize(len as usize, 0);
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok(SourceFingerprint {
+        size: size as i64,
+        modified_ms,
+        sample_md5: to_hex(&hasher.finalize().into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let digest = md5(b"hello");
+        assert_eq!(to_hex(&digest), "5d41402abc4b2a76b9719d911017c592");
+        assert_eq!(from_hex(&to_hex(&digest)), Some(digest));
+        assert_eq!(from_hex("not-hex"), None);
+    }
+
+    #[test]
+    fn test_parse_etag() {
+        assert_eq!(
+            parse_etag("\"5d41402abc4b2a76b9719d911017c592\""),
+            EtagKind::Simple("5d41402abc4b2a76b9719d911017c592".to_string())
+        );
+        assert_eq!(
+            parse_etag("\"5D41402ABC4B2A76B9719D911017C592-3\""),
+            EtagKind::Multipart("5d41402abc4b2a76b9719d911017c592-3".to_string(), 3)
+        );
+        assert_eq!(parse_etag("\"encrypted\""), EtagKind::Opaque);
+    }
+
+    #[test]
+    fn test_file_etag_matches_part_digests() {
+        let path = std::env::temp_dir().join(format!("etag-test-{}", uuid::Uuid::new_v4()));
+        let data: Vec<u8> = (0..2500u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(file_etag(&path, None).unwrap(), to_hex(&md5(&data)));
+        let expected = composite_etag(&[md5(&data[..1000]), md5(&data[1000..2000]), md5(&data[2000..])]);
+        assert_eq!(file_etag(&path, Some(1000)).unwrap(), expected);
+        assert!(etags_match(&format!("\"{}\"", expected), &expected.to_uppercase()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_sees_edits_of_the_same_size() {
+        let path = std::env::temp_dir().join(format!("fingerprint-test-{}", uuid::Uuid::new_v4()));
+        let mut data = vec![7u8; 4 * FINGERPRINT_SAMPLE as usize];
+        std::fs::write(&path, &data).unwrap();
+        let before = fingerprint(&path).unwrap();
+        assert_eq!(before.size, data.len() as i64);
+        assert_eq!(fingerprint(&path).unwrap().sample_md5, before.sample_md5);
+
+        // An edit inside the middle sample changes the hash, not the size
+        let middle = data.len() / 2;
+        data[middle] = 8;
+        std::fs::write(&path, &data).unwrap();
+        let after = fingerprint(&path).unwrap();
+        assert_eq!(after.size, before.size);
+        assert_ne!(after.sample_md5, before.sample_md5);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
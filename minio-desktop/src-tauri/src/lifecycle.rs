@@ -0,0 +1,354 @@
+// Bucket lifecycle rules: validation, and conversion between the app's
+// rule model and the S3 lifecycle configuration. Storage class transitions
+// aren't modelled; they are carried over from the server's rules on write.
+
+use std::collections::HashSet;
+use aws_sdk_s3::primitives::DateTime as S3DateTime;
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, ExpirationStatus,
+    LifecycleExpiration, LifecycleRule as S3LifecycleRule, LifecycleRuleAndOperator,
+    LifecycleRuleFilter, NoncurrentVersionExpiration, Tag,
+};
+use chrono::{DateTime, Utc};
+use crate::error::{AppError, Result};
+use crate::models::{LifecycleRule, LifecycleTag};
+
+/// S3 limits on a lifecycle configuration
+const MAX_RULES: usize = 1000;
+const MAX_ID_LEN: usize = 255;
+
+/// Check rules for mistakes the server would reject, or that would make the
+/// configuration ambiguous, before anything is sent
+pub fn validate(rules: &[LifecycleRule]) -> Result<()> {
+    if rules.len() > MAX_RULES {
+        return Err(invalid(format!("at most {} rules are allowed, got {}", MAX_RULES, rules.len())));
+    }
+
+    let mut ids = HashSet::new();
+    for rule in rules {
+        let id = rule.id.trim();
+        if id.is_empty() {
+            return Err(invalid("every rule needs an ID".to_string()));
+        }
+        if id.len() > MAX_ID_LEN {
+            return Err(invalid(format!("rule ID {} is longer than {} characters", id, MAX_ID_LEN)));
+        }
+        if !ids.insert(id) {
+            return Err(invalid(format!("rule ID {} is used more than once", id)));
+        }
+        validate_rule(rule).map_err(|e| invalid(format!("rule {}: {}", id, e)))?;
+    }
+    Ok(())
+}
+
+fn validate_rule(rule: &LifecycleRule) -> std::result::Result<(), String> {
+    let mut tag_keys = HashSet::new();
+    for tag in &rule.tags {
+        if tag.key.is_empty() {
+            return Err("tag keys can't be empty".to_string());
+        }
+        if !tag_keys.insert(tag.key.as_str()) {
+            return Err(format!("tag {} appears more than once", tag.key));
+        }
+    }
+
+    if matches!(rule.object_size_greater_than, Some(n) if n < 0) || matches!(rule.object_size_less_than, Some(n) if n <= 0) {
+        return Err("object size limits must be positive".to_string());
+    }
+    if let (Some(min), Some(max)) = (rule.object_size_greater_than, rule.object_size_less_than) {
+        if min >= max {
+            return Err("the minimum object size must be below the maximum".to_string());
+        }
+    }
+
+    let has_action = rule.expiration_days.is_some()
+        || rule.expiration_date.is_some()
+        || rule.expired_object_delete_marker
+        || rule.noncurrent_expiration_days.is_some()
+        || rule.abort_incomplete_multipart_days.is_some()
+        || rule.has_transitions;
+    if !has_action {
+        return Err("no action set".to_string());
+    }
+
+    if rule.expiration_days.is_some() && rule.expiration_date.is_some() {
+        return Err("expiration can be set by days or by date, not both".to_string());
+    }
+    if rule.expired_object_delete_marker && (rule.expiration_days.is_some() || rule.expiration_date.is_some()) {
+        return Err("removing expired delete markers can't be combined with an expiration".to_string());
+    }
+    if rule.expired_object_delete_marker && !rule.tags.is_empty() {
+        return Err("removing expired delete markers can't be combined with a tag filter".to_string());
+    }
+    if let Some(date) = rule.expiration_date {
+        if date.timestamp() % 86_400 != 0 {
+            return Err("the expiration date must be at midnight UTC".to_string());
+        }
+    }
+    if rule.newer_noncurrent_versions.is_some() && rule.noncurrent_expiration_days.is_none() {
+        return Err("keeping newer noncurrent versions needs noncurrent expiration days".to_string());
+    }
+    if rule.abort_incomplete_multipart_days.is_some() && !rule.tags.is_empty() {
+        return Err("aborting incomplete uploads can't be combined with a tag filter".to_string());
+    }
+
+    for (what, days) in [
+        ("expiration days", rule.expiration_days),
+        ("noncurrent expiration days", rule.noncurrent_expiration_days),
+        ("newer noncurrent versions", rule.newer_noncurrent_versions),
+        ("days to abort incomplete uploads", rule.abort_incomplete_multipart_days),
+    ] {
+        if matches!(days, Some(n) if n <= 0) {
+            return Err(format!("{} must be positive", what));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::InvalidConfig(format!("Invalid lifecycle configuration: {}", message))
+}
+
+/// Validate rules and build the configuration sent to the server. Each rule
+/// keeps the transitions of the rule with the same ID in `existing`, the
+/// configuration currently on the server.
+pub fn to_configuration(rules: &[LifecycleRule], existing: &[S3LifecycleRule]) -> Result<BucketLifecycleConfiguration> {
+    validate(rules)?;
+    let rules = rules.iter()
+        .map(|rule| {
+            let current = existing.iter().find(|r| r.id() == Some(rule.id.trim()));
+            to_s3_rule(rule, current)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    BucketLifecycleConfiguration::builder()
+        .set_rules(Some(rules))
+        .build()
+        .map_err(|e| AppError::Other(e.to_string()))
+}
+
+fn to_s3_rule(rule: &LifecycleRule, current: Option<&S3LifecycleRule>) -> Result<S3LifecycleRule> {
+    let transitions = current.map(|r| r.transitions().to_vec()).unwrap_or_default();
+    let noncurrent_transitions = current.map(|r| r.noncurrent_version_transitions().to_vec()).unwrap_or_default();
+    if rule.has_transitions && transitions.is_empty() && noncurrent_transitions.is_empty() {
+        return Err(AppError::InvalidConfig(format!(
+            "Rule {} has storage class transitions that are no longer on the server and can't be recreated here; reload the rules",
+            rule.id.trim()
+        )));
+    }
+
+    let tags = rule.tags.iter()
+        .map(|t| Tag::builder().key(&t.key).value(&t.value).build())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Other(e.to_string()))?;
+    let prefix = rule.prefix.clone().unwrap_or_default();
+    let min_size = rule.object_size_greater_than;
+    let max_size = rule.object_size_less_than;
+
+    // A filter holds a single condition; several go inside an `And`
+    let conditions = usize::from(!prefix.is_empty()) + tags.len() + usize::from(min_size.is_some()) + usize::from(max_size.is_some());
+    let filter = if conditions <= 1 {
+        match (tags.first(), min_size, max_size) {
+            (Some(tag), _, _) => LifecycleRuleFilter::builder().tag(tag.clone()).build(),
+            (_, Some(size), _) => LifecycleRuleFilter::builder().object_size_greater_than(size).build(),
+            (_, _, Some(size)) => LifecycleRuleFilter::builder().object_size_less_than(size).build(),
+            _ => LifecycleRuleFilter::builder().prefix(prefix).build(),
+        }
+    } else {
+        LifecycleRuleFilter::builder()
+            .and(LifecycleRuleAndOperator::builder()
+                .set_prefix(Some(prefix).filter(|p| !p.is_empty()))
+                .set_tags(Some(tags).filter(|t| !t.is_empty()))
+                .set_object_size_greater_than(min_size)
+                .set_object_size_less_than(max_size)
+                .build())
+            .build()
+    };
+
+    let expiration = (rule.expiration_days.is_some() || rule.expiration_date.is_some() || rule.expired_object_delete_marker)
+        .then(|| LifecycleExpiration::builder()
+            .set_days(rule.expiration_days)
+            .set_date(rule.expiration_date.map(|d| S3DateTime::from_secs(d.timestamp())))
+            .set_expired_object_delete_marker(Some(true).filter(|_| rule.expired_object_delete_marker))
+            .build());
+    let noncurrent = rule.noncurrent_expiration_days.map(|days| NoncurrentVersionExpiration::builder()
+        .noncurrent_days(days)
+        .set_newer_noncurrent_versions(rule.newer_noncurrent_versions)
+        .build());
+    let abort = rule.abort_incomplete_multipart_days.map(|days| AbortIncompleteMultipartUpload::builder()
+        .days_after_initiation(days)
+        .build());
+
+    S3LifecycleRule::builder()
+        .id(rule.id.trim())
+        .status(if rule.enabled { ExpirationStatus::Enabled } else { ExpirationStatus::Disabled })
+        .filter(filter)
+        .set_expiration(expiration)
+        .set_noncurrent_version_expiration(noncurrent)
+        .set_abort_incomplete_multipart_upload(abort)
+        .set_transitions(Some(transitions).filter(|t| !t.is_empty()))
+        .set_noncurrent_version_transitions(Some(noncurrent_transitions).filter(|t| !t.is_empty()))
+        .build()
+        .map_err(|e| AppError::Other(e.to_string()))
+}
+
+/// Read a rule returned by the server. Transitions aren't modelled, only
+/// flagged; `to_configuration` takes them from the server's rule again.
+pub fn from_s3_rule(rule: &S3LifecycleRule) -> LifecycleRule {
+    let filter = rule.filter();
+    let and = filter.and_then(|f| f.and());
+    #[allow(deprecated)]
+    let prefix = filter.and_then(|f| f.prefix())
+        .or_else(|| and.and_then(|a| a.prefix()))
+        .or_else(|| rule.prefix())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string());
+    let tags = filter.and_then(|f| f.tag()).into_iter()
+        .chain(and.map(|a| a.tags()).unwrap_or_default())
+        .map(|t| LifecycleTag { key: t.key().to_string(), value: t.value().to_string() })
+        .collect();
+    let object_size_greater_than = filter.and_then(|f| f.object_size_greater_than())
+        .or_else(|| and.and_then(|a| a.object_size_greater_than()));
+    let object_size_less_than = filter.and_then(|f| f.object_size_less_than())
+        .or_else(|| and.and_then(|a| a.object_size_less_than()));
+
+    let expiration = rule.expiration();
+    let noncurrent = rule.noncurrent_version_expiration();
+    LifecycleRule {
+        id: rule.id().unwrap_or_default().to_string(),
+        enabled: *rule.status() == ExpirationStatus::Enabled,
+        prefix,
+        tags,
+        object_size_greater_than,
+        object_size_less_than,
+        expiration_days: expiration.and_then(|e| e.days()),
+        expiration_date: expiration.and_then(|e| e.date())
+            .and_then(|d| DateTime::<Utc>::from_timestamp(d.secs(), 0)),
+        expired_object_delete_marker: expiration.and_then(|e| e.expired_object_delete_marker()).unwrap_or(false),
+        noncurrent_expiration_days: noncurrent.and_then(|n| n.noncurrent_days()),
+        newer_noncurrent_versions: noncurrent.and_then(|n| n.newer_noncurrent_versions()),
+        abort_incomplete_multipart_days: rule.abort_incomplete_multipart_upload()
+            .and_then(|a| a.days_after_initiation()),
+        has_transitions: !rule.transitions().is_empty() || !rule.noncurrent_version_transitions().is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::types::{Transition, TransitionStorageClass};
+    use chrono::TimeZone;
+
+    fn rule(id: &str) -> LifecycleRule {
+        LifecycleRule {
+            id: id.to_string(),
+            enabled: true,
+            prefix: Some("logs/".to_string()),
+            tags: vec![],
+            object_size_greater_than: None,
+            object_size_less_than: None,
+            expiration_days: Some(30),
+            expiration_date: None,
+            expired_object_delete_marker: false,
+            noncurrent_expiration_days: Some(7),
+            newer_noncurrent_versions: None,
+            abort_incomplete_multipart_days: Some(2),
+            has_transitions: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicate_ids() {
+        assert!(validate(&[rule("logs"), rule("archive")]).is_ok());
+        assert!(validate(&[rule("logs"), rule(" logs ")]).is_err());
+        assert!(validate(&[rule("")]).is_err());
+    }
+
+    #[test]
+    fn test_validate_rule_actions() {
+        let mut no_action = rule("a");
+        no_action.expiration_days = None;
+        no_action.noncurrent_expiration_days = None;
+        no_action.abort_incomplete_multipart_days = None;
+        assert!(validate(&[no_action]).is_err());
+
+        let mut days_and_date = rule("a");
+        days_and_date.expiration_date = Some(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap());
+        assert!(validate(&[days_and_date.clone()]).is_err());
+        days_and_date.expiration_days = None;
+        assert!(validate(&[days_and_date.clone()]).is_ok());
+        days_and_date.expiration_date = Some(Utc.with_ymd_and_hms(2030, 1, 1, 12, 0, 0).unwrap());
+        assert!(validate(&[days_and_date]).is_err());
+
+        let mut negative = rule("a");
+        negative.noncurrent_expiration_days = Some(0);
+        assert!(validate(&[negative]).is_err());
+
+        let mut tagged_abort = rule("a");
+        tagged_abort.tags = vec![LifecycleTag { key: "team".to_string(), value: "ops".to_string() }];
+        assert!(validate(&[tagged_abort.clone()]).is_err());
+        tagged_abort.abort_incomplete_multipart_days = None;
+        assert!(validate(&[tagged_abort]).is_ok());
+    }
+
+    #[test]
+    fn test_rules_round_trip() {
+        let mut tagged = rule("tagged");
+        tagged.abort_incomplete_multipart_days = None;
+        tagged.tags = vec![
+            LifecycleTag { key: "team".to_string(), value: "ops".to_string() },
+            LifecycleTag { key: "tier".to_string(), value: "cold".to_string() },
+        ];
+        let mut dated = rule("dated");
+        dated.prefix = None;
+        dated.enabled = false;
+        dated.expiration_days = None;
+        dated.expiration_date = Some(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap());
+        dated.newer_noncurrent_versions = Some(3);
+
+        let mut sized = rule("sized");
+        sized.prefix = None;
+        sized.object_size_less_than = Some(1024);
+
+        let rules = vec![rule("plain"), tagged, dated, sized];
+        let configuration = to_configuration(&rules, &[]).unwrap();
+        let read_back: Vec<LifecycleRule> = configuration.rules().iter().map(from_s3_rule).collect();
+        assert_eq!(read_back, rules);
+    }
+
+    #[test]
+    fn test_transitions_and_size_filters_survive_a_round_trip() {
+        // A rule made with another tool: large objects under `logs/` move
+        // to cold storage after 30 days and expire after a year
+        let server_rule = S3LifecycleRule::builder()
+            .id("archive")
+            .status(ExpirationStatus::Enabled)
+            .filter(LifecycleRuleFilter::builder()
+                .and(LifecycleRuleAndOperator::builder()
+                    .prefix("logs/")
+                    .object_size_greater_than(1024 * 1024)
+                    .build())
+                .build())
+            .transitions(Transition::builder().days(30).storage_class(TransitionStorageClass::Glacier).build())
+            .expiration(LifecycleExpiration::builder().days(365).build())
+            .build()
+            .unwrap();
+
+        let mut read = from_s3_rule(&server_rule);
+        assert!(read.has_transitions);
+        assert_eq!(read.prefix.as_deref(), Some("logs/"));
+        assert_eq!(read.object_size_greater_than, Some(1024 * 1024));
+
+        // Edit what the app models, then write back
+        read.expiration_days = Some(400);
+        let configuration = to_configuration(&[read.clone()], std::slice::from_ref(&server_rule)).unwrap();
+        let written = &configuration.rules()[0];
+        assert_eq!(written.transitions(), server_rule.transitions());
+        let and = written.filter().and_then(|f| f.and()).unwrap();
+        assert_eq!(and.prefix(), Some("logs/"));
+        assert_eq!(and.object_size_greater_than(), Some(1024 * 1024));
+        assert_eq!(written.expiration().and_then(|e| e.days()), Some(400));
+
+        // Without the server's rule the transitions can't be rebuilt
+        assert!(to_configuration(&[read], &[]).is_err());
+    }
+}
@@ -9,16 +9,29 @@ mod streaming;
 mod error;
 mod transfer;
 mod encryption;
+mod tls;
+mod vault;
+mod folder;
+mod retry;
+mod throttle;
+mod stats;
+mod integrity;
+mod conflict;
+mod lifecycle;
 
 use tauri::{Manager, State};
 use std::sync::Arc;
+use std::collections::HashMap;
+use tokio_util::sync::CancellationToken;
 use tokio::sync::Mutex;
 
 pub struct AppState {
     db: Arc<Mutex<db::Database>>,
     minio_client: Arc<Mutex<Option<minio::MinioClient>>>,
+    active_profile: Arc<Mutex<Option<String>>>,
+    listings: Arc<Mutex<HashMap<String, CancellationToken>>>,
     stream_server: Arc<Mutex<Option<streaming::StreamServer>>>,
-    transfer_manager: Arc<Mutex<transfer::TransferManager>>,
+    transfer_manager: Arc<transfer::TransferManager>,
 }
 
 fn main() {
@@ -33,23 +46,61 @@ fn main() {
             commands::connection::test_connection,
             commands::connection::save_connection,
             commands::connection::get_connection,
+            commands::connection::list_profiles,
+            commands::connection::get_active_profile,
+            commands::connection::create_profile,
+            commands::connection::update_profile,
+            commands::connection::rename_profile,
+            commands::connection::delete_profile,
+            commands::connection::duplicate_profile,
+            commands::connection::set_default_profile,
+            commands::connection::switch_profile,
             commands::bucket::list_buckets,
             commands::bucket::create_bucket,
             commands::bucket::delete_bucket,
+            commands::bucket::get_bucket_versioning,
+            commands::bucket::set_bucket_versioning,
+            commands::bucket::get_bucket_lifecycle,
+            commands::bucket::put_bucket_lifecycle,
+            commands::bucket::delete_bucket_lifecycle,
             commands::object::list_objects,
+            commands::object::list_objects_page,
+            commands::object::stream_objects,
+            commands::object::cancel_object_listing,
 
             commands::object::upload_file,
+            commands::object::upload_folder,
             commands::object::download_file,
+            commands::object::download_folder,
             commands::object::delete_object,
+            commands::object::list_object_versions,
+            commands::object::restore_object_version,
+            commands::object::delete_object_version,
             commands::object::get_presigned_url,
             commands::object::get_object_data,
             commands::transfer::get_transfer_tasks,
             commands::transfer::pause_task,
             commands::transfer::resume_task,
+            commands::transfer::resume_download,
             commands::transfer::cancel_task,
             commands::transfer::cancel_all_tasks,
+            commands::transfer::get_transfer_jobs,
+            commands::transfer::get_job_tasks,
+            commands::transfer::pause_job,
+            commands::transfer::resume_job,
+            commands::transfer::cancel_job,
+            commands::transfer::delete_job,
+            commands::transfer::set_task_priority,
+            commands::transfer::set_task_bandwidth_limit,
+            commands::transfer::reorder_tasks,
+            commands::transfer::get_transfer_stats,
+            commands::transfer::get_transfer_settings,
+            commands::transfer::update_transfer_settings,
             commands::transfer::delete_task,
             commands::transfer::delete_completed_tasks,
+            commands::multipart::list_multipart_uploads,
+            commands::multipart::abort_multipart_upload,
+            commands::multipart::abort_stale_multipart_uploads,
             commands::streaming::get_stream_url,
             commands::streaming::check_stream_server,
             commands::encryption::generate_encryption_key,
@@ -57,6 +108,11 @@ fn main() {
             commands::encryption::get_encryption_key,
             commands::encryption::set_encryption_enabled,
             commands::encryption::validate_encryption_key,
+            commands::vault::get_vault_status,
+            commands::vault::setup_vault,
+            commands::vault::unlock_vault,
+            commands::vault::lock_vault,
+            commands::vault::set_vault_idle_timeout,
         ])
         .setup(|app| {
             // Initialize database
@@ -68,21 +124,86 @@ fn main() {
             let db_path = app_dir.join("data.db");
             
             let db = db::Database::new(db_path.to_str().unwrap())?;
+            // With a locked vault the default profile's secret is unavailable
+            // until the user unlocks it; the connection is made then instead
+            let default_profile = match db.get_default_profile() {
+                Ok(profile) => profile,
+                Err(e) => {
+                    eprintln!("[App] Default profile not loaded at startup: {}", e);
+                    None
+                }
+            };
+            
+            let transfer_settings = db.get_transfer_settings().unwrap_or_default();
+            
+            // Tasks still marked running were cut off when the app last quit;
+            // they are reconciled with the server once a connection is made
+            match db.mark_running_tasks_interrupted() {
+                Ok(0) => {}
+                Ok(count) => eprintln!("[App] {} transfer task(s) were interrupted", count),
+                Err(e) => eprintln!("[App] Failed to mark interrupted tasks: {}", e),
+            }
             
             // Setup app state
             let db_arc = Arc::new(Mutex::new(db));
             let minio_arc = Arc::new(Mutex::new(None));
-            let transfer_manager = transfer::TransferManager::new(db_arc.clone(), minio_arc.clone());
+            let active_profile_arc = Arc::new(Mutex::new(None));
+            let transfer_manager = Arc::new(transfer::TransferManager::new(
+                app.handle(),
+                db_arc.clone(),
+                minio_arc.clone(),
+                active_profile_arc.clone(),
+                transfer_settings,
+            ));
+            
+            // Report aggregate throughput while transfers run
+            let stats_manager = transfer_manager.clone();
+            tauri::async_runtime::spawn(async move {
+                stats_manager.report_stats().await;
+            });
+            
+            // Auto-lock the vault after its idle period
+            let vault_db = db_arc.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    vault_db.lock().await.lock_vault_if_idle();
+                }
+            });
             
             let state = AppState {
                 db: db_arc,
-                minio_client: minio_arc,
+                minio_client: minio_arc.clone(),
+                active_profile: active_profile_arc.clone(),
+                listings: Arc::new(Mutex::new(HashMap::new())),
                 stream_server: Arc::new(Mutex::new(None)),
-                transfer_manager: Arc::new(Mutex::new(transfer_manager)),
+                transfer_manager: transfer_manager.clone(),
             };
             
             app.manage(state);
             
+            // Connect to the default profile in the background
+            if let Some(profile) = default_profile {
+                tauri::async_runtime::spawn(async move {
+                    match minio::MinioClient::new(profile.to_config()).await {
+                        Ok(client) => {
+                            // The profile is switched under the client lock so the pair stays consistent
+                            let mut minio_client = minio_arc.lock().await;
+                            *minio_client = Some(client);
+                            *active_profile_arc.lock().await = Some(profile.profile_id.clone());
+                            drop(minio_client);
+                            eprintln!("[App] Connected to default profile: {}", profile.name);
+                            // Pick up tasks queued in an earlier session
+                            transfer_manager.schedule();
+                        }
+                        Err(e) => {
+                            eprintln!("[App] Failed to connect to default profile {}: {}", profile.name, e);
+                        }
+                    }
+                });
+            }
+            
             // Log application startup
             eprintln!("[App] MinIO Desktop application started");
             
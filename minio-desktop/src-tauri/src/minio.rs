@@ -4,29 +4,26 @@ use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::config::timeout::TimeoutConfig;
 use std::time::Duration;
 use crate::error::{AppError, Result};
-use crate::models::{ConnectionConfig, BucketInfo, ObjectInfo};
-use rustls::client::{ServerCertVerifier, ServerCertVerified};
-use rustls::{Certificate, ServerName, Error as RustlsError};
-
-// Custom certificate verifier that accepts all certificates (including self-signed)
-struct NoCertificateVerification;
-
-impl ServerCertVerifier for NoCertificateVerification {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &Certificate,
-        _intermediates: &[Certificate],
-        _server_name: &ServerName,
-        _scts: &mut dyn Iterator<Item = &[u8]>,
-        _ocsp_response: &[u8],
-        _now: std::time::SystemTime,
-    ) -> std::result::Result<ServerCertVerified, RustlsError> {
-        Ok(ServerCertVerified::assertion())
-    }
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::types::{BucketVersioningStatus, VersioningConfiguration};
+use crate::encryption::SseCEncryption;
+use crate::lifecycle;
+use crate::models::{ConnectionConfig, BucketInfo, LifecycleRule, MultipartUploadInfo, ObjectInfo, ObjectListPage, ObjectVersionInfo, ObjectVersionPage, VersioningStatus};
+/// Options for a single ListObjectsV2 request
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ListOptions<'a> {
+    pub prefix: Option<&'a str>,
+    /// List every key under the prefix instead of one "folder" level
+    pub recursive: bool,
+    pub continuation_token: Option<&'a str>,
+    pub start_after: Option<&'a str>,
+    pub max_keys: Option<i32>,
 }
 
+#[derive(Clone)]
 pub struct MinioClient {
     client: Client,
+    http_client: reqwest::Client,
     config: ConnectionConfig,
 }
 
@@ -55,11 +52,15 @@ impl MinioClient {
             .read_timeout(Duration::from_secs(300)) // 5 minutes for read
             .build();
         
-        // Create a custom TLS connector that accepts self-signed certificates
-        let tls_config = rustls::ClientConfig::builder()
-            .with_safe_defaults()
-            .with_custom_certificate_verifier(std::sync::Arc::new(NoCertificateVerification))
-            .with_no_client_auth();
+        // Build the TLS configuration from the connection's trust policy
+        let tls_config = crate::tls::build_client_config(&config.tls)?;
+        
+        // Plain HTTP client for presigned range downloads, sharing the same trust policy
+        let http_client = reqwest::Client::builder()
+            .use_preconfigured_tls(tls_config.clone())
+            .connect_timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| AppError::Other(format!("Failed to build HTTP client: {}", e)))?;
         
         let https_connector = hyper_rustls::HttpsConnectorBuilder::new()
             .with_tls_config(tls_config)
@@ -87,7 +88,7 @@ impl MinioClient {
         let s3_config = s3_config_builder.build();
         let client = Client::from_conf(s3_config);
         
-        Ok(MinioClient { client, config })
+        Ok(MinioClient { client, http_client, config })
     }
     
     pub async fn list_buckets(&self) -> Result<Vec<BucketInfo>> {
@@ -97,7 +98,7 @@ impl MinioClient {
             .await
             .map_err(|e| {
                 eprintln!("S3 ListBuckets Error: {:?}", e);
-                AppError::S3(e.to_string())
+                s3_error(e)
             })?;
         
         let buckets = resp.buckets()
@@ -167,31 +168,69 @@ impl MinioClient {
         Ok(())
     }
     
-    pub async fn list_objects(&self, bucket: &str, prefix: Option<&str>) -> Result<Vec<ObjectInfo>> {
+    /// List every object under the prefix, following continuation tokens
+    pub async fn list_objects(&self, bucket: &str, prefix: Option<&str>, start_after: Option<&str>) -> Result<Vec<ObjectInfo>> {
         println!("Listing objects in bucket: {}, prefix: {:?}", bucket, prefix);
         
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        
+        loop {
+            let page = self.list_objects_page(bucket, &ListOptions {
+                prefix,
+                recursive: false,
+                continuation_token: continuation_token.as_deref(),
+                start_after,
+                max_keys: None,
+            }).await?;
+            
+            objects.extend(page.objects);
+            
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => continuation_token = Some(token),
+                _ => break,
+            }
+        }
+        
+        println!("Total objects found: {}", objects.len());
+        Ok(objects)
+    }
+    
+    /// List a single page of objects. Pass the returned `next_continuation_token`
+    /// back in to fetch the next page.
+    pub async fn list_objects_page(&self, bucket: &str, options: &ListOptions<'_>) -> Result<ObjectListPage> {
         let mut request = self.client
             .list_objects_v2()
-            .bucket(bucket);
+            .bucket(bucket)
+            // 设置 fetch-owner 为 false，避免额外开销
+            .fetch_owner(false);
 
-        // 设置 prefix 参数
-        if let Some(p) = prefix {
+        if let Some(p) = options.prefix {
             request = request.prefix(p);
-            println!("Using prefix: {}", p);
         }
 
-        // 设置 fetch-owner 为 false，避免额外开销
-        request = request.fetch_owner(false);
+        // 非递归时设置 delimiter 为 "/"，以实现文件夹功能
+        if !options.recursive {
+            request = request.delimiter("/");
+        }
         
-        // 设置 delimiter 为 "/"，以实现文件夹功能
-        request = request.delimiter("/");
+        if let Some(token) = options.continuation_token {
+            request = request.continuation_token(token);
+        } else if let Some(start_after) = options.start_after {
+            // start_after is only honoured on the first request
+            request = request.start_after(start_after);
+        }
+        
+        if let Some(max_keys) = options.max_keys {
+            request = request.max_keys(max_keys);
+        }
 
         let resp = request
             .send()
             .await
             .map_err(|e| {
                 eprintln!("Failed to list objects: {:?}", e);
-                AppError::S3(e.to_string())
+                s3_error(e)
             })?;
 
         let mut objects = Vec::new();
@@ -199,35 +238,36 @@ impl MinioClient {
         // Add directories (common prefixes)
         for prefix in resp.common_prefixes() {
             if let Some(p) = prefix.prefix() {
-                println!("Found directory: {}", p);
                 objects.push(ObjectInfo {
                     key: p.to_string(),
                     size: 0,
                     last_modified: None,
                     content_type: None,
                     is_dir: true,
+                    etag: None,
                 });
             }
         }
 
         // Add files
         for obj in resp.contents() {
-            let key = obj.key().unwrap_or("");
-            let size = obj.size().unwrap_or(0);
-            println!("Found file: {}, size: {}", key, size);
             objects.push(ObjectInfo {
-                key: key.to_string(),
-                size,
+                key: obj.key().unwrap_or("").to_string(),
+                size: obj.size().unwrap_or(0),
                 last_modified: obj.last_modified()
                     .and_then(|dt| chrono::DateTime::parse_from_rfc3339(&dt.to_string()).ok())
                     .map(|dt| dt.with_timezone(&chrono::Utc)),
                 content_type: None, // content_type method doesn't exist on this Object type
                 is_dir: false,
+                etag: obj.e_tag().map(|e| e.to_string()),
             });
         }
 
-        println!("Total objects found: {}", objects.len());
-        Ok(objects)
+        Ok(ObjectListPage {
+            objects,
+            next_continuation_token: resp.next_continuation_token().map(|t| t.to_string()),
+            is_truncated: resp.is_truncated().unwrap_or(false),
+        })
     }
     
     pub async fn delete_object(&self, bucket: &str, key: &str) -> Result<()> {
@@ -242,7 +282,320 @@ impl MinioClient {
         Ok(())
     }
     
+    pub async fn get_bucket_versioning(&self, bucket: &str) -> Result<VersioningStatus> {
+        let resp = self.client
+            .get_bucket_versioning()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(match resp.status() {
+            Some(BucketVersioningStatus::Enabled) => VersioningStatus::Enabled,
+            Some(BucketVersioningStatus::Suspended) => VersioningStatus::Suspended,
+            _ => VersioningStatus::Disabled,
+        })
+    }
+
+    /// Enable versioning, or suspend it. Versioning can't be switched off
+    /// again once enabled; suspended buckets keep their existing versions.
+    pub async fn set_bucket_versioning(&self, bucket: &str, enabled: bool) -> Result<()> {
+        let status = if enabled { BucketVersioningStatus::Enabled } else { BucketVersioningStatus::Suspended };
+        self.client
+            .put_bucket_versioning()
+            .bucket(bucket)
+            .versioning_configuration(VersioningConfiguration::builder().status(status).build())
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(())
+    }
+
+    /// Lifecycle rules of a bucket; a bucket without a configuration has none
+    pub async fn get_bucket_lifecycle(&self, bucket: &str) -> Result<Vec<LifecycleRule>> {
+        let rules = self.lifecycle_rules(bucket).await?;
+        Ok(rules.iter().map(lifecycle::from_s3_rule).collect())
+    }
+
+    /// Replace the bucket's lifecycle rules. Rules are validated before the
+    /// request is sent, and keep the storage class transitions of the
+    /// server's rules with the same IDs. An empty list removes the
+    /// configuration.
+    pub async fn put_bucket_lifecycle(&self, bucket: &str, rules: &[LifecycleRule]) -> Result<()> {
+        if rules.is_empty() {
+            return self.delete_bucket_lifecycle(bucket).await;
+        }
+        let existing = self.lifecycle_rules(bucket).await?;
+        let configuration = lifecycle::to_configuration(rules, &existing)?;
+        self.client
+            .put_bucket_lifecycle_configuration()
+            .bucket(bucket)
+            .lifecycle_configuration(configuration)
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(())
+    }
+
+    async fn lifecycle_rules(&self, bucket: &str) -> Result<Vec<aws_sdk_s3::types::LifecycleRule>> {
+        match self.client
+            .get_bucket_lifecycle_configuration()
+            .bucket(bucket)
+            .send()
+            .await
+        {
+            Ok(resp) => Ok(resp.rules().to_vec()),
+            Err(e) if e.as_service_error().and_then(|se| se.code()) == Some("NoSuchLifecycleConfiguration") => Ok(Vec::new()),
+            Err(e) => Err(s3_error(e)),
+        }
+    }
+
+    pub async fn delete_bucket_lifecycle(&self, bucket: &str) -> Result<()> {
+        self.client
+            .delete_bucket_lifecycle()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(())
+    }
+
+    /// One page of object versions and delete markers, newest first for
+    /// each key. Pass the returned markers back to continue.
+    pub async fn list_object_versions_page(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        key_marker: Option<&str>,
+        version_id_marker: Option<&str>,
+        max_keys: Option<i32>,
+    ) -> Result<ObjectVersionPage> {
+        let resp = self.client
+            .list_object_versions()
+            .bucket(bucket)
+            .set_prefix(prefix.map(|p| p.to_string()))
+            .set_key_marker(key_marker.map(|m| m.to_string()))
+            .set_version_id_marker(version_id_marker.map(|m| m.to_string()))
+            .set_max_keys(max_keys)
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        let mut versions: Vec<ObjectVersionInfo> = resp.versions()
+            .iter()
+            .map(|v| ObjectVersionInfo {
+                key: v.key().unwrap_or("").to_string(),
+                version_id: v.version_id().unwrap_or("null").to_string(),
+                is_latest: v.is_latest().unwrap_or(false),
+                is_delete_marker: false,
+                size: v.size().unwrap_or(0),
+                last_modified: v.last_modified().and_then(to_utc),
+                etag: v.e_tag().map(|e| e.to_string()),
+            })
+            .collect();
+        versions.extend(resp.delete_markers().iter().map(|m| ObjectVersionInfo {
+            key: m.key().unwrap_or("").to_string(),
+            version_id: m.version_id().unwrap_or("null").to_string(),
+            is_latest: m.is_latest().unwrap_or(false),
+            is_delete_marker: true,
+            size: 0,
+            last_modified: m.last_modified().and_then(to_utc),
+            etag: None,
+        }));
+        // Versions and delete markers come back as separate lists
+        versions.sort_by(|a, b| a.key.cmp(&b.key).then(b.last_modified.cmp(&a.last_modified)));
+
+        Ok(ObjectVersionPage {
+            versions,
+            next_key_marker: resp.next_key_marker().map(|m| m.to_string()),
+            next_version_id_marker: resp.next_version_id_marker().map(|m| m.to_string()),
+            is_truncated: resp.is_truncated().unwrap_or(false),
+        })
+    }
+
+    /// Make an old version the latest again by copying it over the key.
+    /// The copy is a new version; history is kept. Limited to objects of
+    /// up to 5 GiB, the S3 limit for a single copy.
+    pub async fn restore_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        let copy_source = format!(
+            "{}/{}?versionId={}",
+            bucket,
+            encode_copy_source(key),
+            encode_copy_source(version_id)
+        );
+        self.client
+            .copy_object()
+            .bucket(bucket)
+            .key(key)
+            .copy_source(copy_source)
+            .set_copy_source_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+            .set_copy_source_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+            .set_copy_source_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()))
+            .set_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+            .set_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+            .set_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()))
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(())
+    }
+
+    /// Permanently delete one version (or delete marker) of an object
+    pub async fn delete_object_version(&self, bucket: &str, key: &str, version_id: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(s3_error)?;
+
+        Ok(())
+    }
+
+    /// List the bucket's incomplete multipart uploads with the size of the
+    /// parts each one holds
+    pub async fn list_multipart_uploads(&self, bucket: &str) -> Result<Vec<MultipartUploadInfo>> {
+        let mut uploads = Vec::new();
+        let mut key_marker: Option<String> = None;
+        let mut upload_id_marker: Option<String> = None;
+        loop {
+            let resp = self.client
+                .list_multipart_uploads()
+                .bucket(bucket)
+                .set_key_marker(key_marker.clone())
+                .set_upload_id_marker(upload_id_marker.clone())
+                .send()
+                .await
+                .map_err(s3_error)?;
+
+            for upload in resp.uploads() {
+                let (Some(key), Some(upload_id)) = (upload.key(), upload.upload_id()) else {
+                    continue;
+                };
+                uploads.push(MultipartUploadInfo {
+                    bucket: bucket.to_string(),
+                    key: key.to_string(),
+                    upload_id: upload_id.to_string(),
+                    initiated: upload.initiated()
+                        .and_then(|dt| chrono::DateTime::parse_from_rfc3339(&dt.to_string()).ok())
+                        .map(|dt| dt.with_timezone(&chrono::Utc)),
+                    size: None,
+                    part_count: None,
+                    task_id: None,
+                });
+            }
+
+            if resp.is_truncated() != Some(true) {
+                break;
+            }
+            key_marker = resp.next_key_marker().map(|m| m.to_string());
+            upload_id_marker = resp.next_upload_id_marker().map(|m| m.to_string());
+            if key_marker.is_none() && upload_id_marker.is_none() {
+                break;
+            }
+        }
+
+        for upload in uploads.iter_mut() {
+            match self.multipart_upload_size(bucket, &upload.key, &upload.upload_id).await {
+                Ok((size, parts)) => {
+                    upload.size = Some(size);
+                    upload.part_count = Some(parts);
+                }
+                Err(e) => eprintln!("Failed to list parts of upload {}: {}", upload.upload_id, e),
+            }
+        }
+
+        Ok(uploads)
+    }
+
+    /// Total bytes and number of parts stored for a multipart upload
+    pub async fn multipart_upload_size(&self, bucket: &str, key: &str, upload_id: &str) -> Result<(i64, i64)> {
+        let mut size = 0;
+        let mut parts = 0;
+        let mut marker: Option<String> = None;
+        loop {
+            let resp = self.client
+                .list_parts()
+                .bucket(bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .set_part_number_marker(marker.clone())
+                .send()
+                .await
+                .map_err(s3_error)?;
+
+            for part in resp.parts() {
+                size += part.size().unwrap_or(0);
+                parts += 1;
+            }
+
+            match resp.next_part_number_marker() {
+                Some(next) if resp.is_truncated() == Some(true) => marker = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        Ok((size, parts))
+    }
+
+    /// Abort a multipart upload, discarding its parts. An upload that no
+    /// longer exists counts as aborted.
+    pub async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        match self.client
+            .abort_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(e) if e.as_service_error().and_then(|se| se.code()) == Some("NoSuchUpload") => Ok(()),
+            Err(e) => Err(s3_error(e)),
+        }
+    }
+    
     pub fn get_client(&self) -> &Client {
         &self.client
     }
+    
+    /// HTTP client for presigned requests, configured with the connection's TLS policy
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.http_client
+    }
+}
+
+/// Percent-encode a key for the `x-amz-copy-source` header, keeping `/`
+fn encode_copy_source(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Convert an SDK error into an AppError including the full source chain,
+/// so transport failures such as TLS verification errors are not reduced
+/// to "dispatch failure".
+pub fn s3_error<E: std::error::Error>(e: E) -> AppError {
+    AppError::S3(aws_sdk_s3::error::DisplayErrorContext(e).to_string())
+}
+
+/// Convert an SDK timestamp to chrono without a round trip through text
+fn to_utc(dt: &aws_sdk_s3::primitives::DateTime) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::from_timestamp(dt.secs(), dt.subsec_nanos())
 }
@@ -8,9 +8,78 @@ pub struct ConnectionConfig {
     pub access_key: String,
     pub secret_key: String,
     pub use_ssl: bool,
+    #[serde(default)]
+    pub tls: TlsPolicy,
     pub last_connected: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    /// Verify against the operating system's root certificates
+    #[default]
+    System,
+    /// System roots plus a user-supplied PEM CA bundle
+    CustomCa,
+    /// Accept only leaf certificates matching a SHA-256 fingerprint
+    Pinned,
+    /// Accept any certificate (self-signed, expired, wrong host)
+    Insecure,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TlsPolicy {
+    pub mode: TlsMode,
+    #[serde(default)]
+    pub ca_bundle_pem: Option<String>,
+    #[serde(default)]
+    pub pinned_sha256: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    pub profile_id: String,
+    pub name: String,
+    pub endpoint: String,
+    pub port: u16,
+    pub access_key: String,
+    pub secret_key: String,
+    pub use_ssl: bool,
+    #[serde(default)]
+    pub tls: TlsPolicy,
+    pub is_default: bool,
+    pub last_connected: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Connection settings of a profile as entered in the connection form
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileInput {
+    pub endpoint: String,
+    pub port: u16,
+    pub access_key: String,
+    pub secret_key: String,
+    pub use_ssl: bool,
+    #[serde(default)]
+    pub tls: Option<TlsPolicy>,
+}
+
+impl ConnectionProfile {
+    /// Build the client configuration for this profile
+    pub fn to_config(&self) -> ConnectionConfig {
+        ConnectionConfig {
+            endpoint: self.endpoint.clone(),
+            port: self.port,
+            access_key: self.access_key.clone(),
+            secret_key: self.secret_key.clone(),
+            use_ssl: self.use_ssl,
+            tls: self.tls.clone(),
+            last_connected: self.last_connected,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BucketInfo {
     pub name: String,
@@ -24,11 +93,124 @@ pub struct ObjectInfo {
     pub last_modified: Option<DateTime<Utc>>,
     pub content_type: Option<String>,
     pub is_dir: bool,
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+/// Versioning state of a bucket; `Disabled` if it was never enabled
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersioningStatus {
+    Disabled,
+    Enabled,
+    Suspended,
+}
+
+/// One version of an object, or a delete marker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectVersionInfo {
+    pub key: String,
+    pub version_id: String,
+    pub is_latest: bool,
+    /// Delete markers hide the object and have no content of their own
+    pub is_delete_marker: bool,
+    pub size: i64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectVersionPage {
+    pub versions: Vec<ObjectVersionInfo>,
+    pub next_key_marker: Option<String>,
+    pub next_version_id_marker: Option<String>,
+    pub is_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectListPage {
+    pub objects: Vec<ObjectInfo>,
+    pub next_continuation_token: Option<String>,
+    pub is_truncated: bool,
+}
+
+/// A bucket lifecycle rule. It applies to objects under `prefix` that carry
+/// all of `tags`; an empty filter covers the whole bucket.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LifecycleRule {
+    pub id: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<LifecycleTag>,
+    /// Only objects larger than this many bytes
+    #[serde(default)]
+    pub object_size_greater_than: Option<i64>,
+    /// Only objects smaller than this many bytes
+    #[serde(default)]
+    pub object_size_less_than: Option<i64>,
+    /// Expire current versions this many days after creation...
+    #[serde(default)]
+    pub expiration_days: Option<i32>,
+    /// ...or on this date, which must be midnight UTC
+    #[serde(default)]
+    pub expiration_date: Option<DateTime<Utc>>,
+    /// Remove delete markers that no longer hide any version
+    #[serde(default)]
+    pub expired_object_delete_marker: bool,
+    /// Expire versions this many days after they stop being current
+    #[serde(default)]
+    pub noncurrent_expiration_days: Option<i32>,
+    /// Keep this many of the newest noncurrent versions regardless of age
+    #[serde(default)]
+    pub newer_noncurrent_versions: Option<i32>,
+    /// Abort multipart uploads still incomplete this many days after they began
+    #[serde(default)]
+    pub abort_incomplete_multipart_days: Option<i32>,
+    /// The server's rule also moves objects between storage classes. The app
+    /// can't edit transitions; saving keeps those of the server's rule with
+    /// the same ID.
+    #[serde(default)]
+    pub has_transitions: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LifecycleTag {
+    pub key: String,
+    pub value: String,
+}
+
+/// A multipart upload the server still holds: never completed or aborted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultipartUploadInfo {
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub initiated: Option<DateTime<Utc>>,
+    /// Bytes and parts stored so far; `None` if the parts couldn't be listed
+    pub size: Option<i64>,
+    pub part_count: Option<i64>,
+    /// Local transfer task that started the upload, if it still exists
+    pub task_id: Option<String>,
+}
+
+/// Payload of the `object-list-batch` event emitted by streamed listings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectListBatch {
+    pub listing_id: String,
+    pub objects: Vec<ObjectInfo>,
+    pub total: usize,
+    pub done: bool,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferTask {
     pub task_id: String,
+    /// Connection profile the task was queued under; it only runs there
+    #[serde(default)]
+    pub profile_id: Option<String>,
     pub task_type: TaskType,
     pub file_name: String,
     pub local_path: String,
@@ -46,6 +228,131 @@ pub struct TransferTask {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
+    /// Higher priority tasks leave the pending queue first
+    #[serde(default)]
+    pub priority: i32,
+    /// Order among pending tasks of the same priority
+    #[serde(default)]
+    pub queue_position: i64,
+    /// ETag of the object when a download began; resumed chunks must match it
+    #[serde(default)]
+    pub remote_etag: Option<String>,
+    /// Parent job for tasks created by a folder transfer
+    #[serde(default)]
+    pub job_id: Option<String>,
+    /// Part/range attempts that failed transiently and were retried
+    #[serde(default)]
+    pub retry_count: i32,
+    /// Per-task bandwidth limit in bytes per second, on top of the global limit
+    #[serde(default)]
+    pub bandwidth_limit: Option<i64>,
+    /// MD5 of the SSE-C key the task was started with; resuming needs the same key
+    #[serde(default)]
+    pub encryption_key_md5: Option<String>,
+    /// What to do when the target already exists
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// State of an upload's source file when its first byte was sent
+    #[serde(default)]
+    pub source_fingerprint: Option<SourceFingerprint>,
+    /// Object version a download fetches; `None` is the latest
+    #[serde(default)]
+    pub version_id: Option<String>,
+    /// Time spent running in milliseconds, excluding queued and paused time
+    #[serde(default)]
+    pub active_ms: i64,
+    /// Bytes per second over the active time; kept after completion
+    #[serde(default)]
+    pub average_speed: f64,
+    /// Recent bytes per second; zero unless the task is running
+    #[serde(default)]
+    pub speed: f64,
+    /// Estimated seconds left at the current speed, while running
+    #[serde(default)]
+    pub eta_seconds: Option<i64>,
+}
+
+/// Payload of the `transfer-progress` event, emitted as parts finish and
+/// whenever a task changes status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub task_id: String,
+    pub job_id: Option<String>,
+    pub task_type: TaskType,
+    pub status: TaskStatus,
+    pub file_size: i64,
+    pub transferred_bytes: i64,
+    pub completed_parts: i32,
+    pub total_parts: i32,
+    pub retry_count: i32,
+    pub error_message: Option<String>,
+    pub active_ms: i64,
+    pub average_speed: f64,
+    pub speed: f64,
+    pub eta_seconds: Option<i64>,
+}
+
+impl From<&TransferTask> for TransferProgress {
+    fn from(task: &TransferTask) -> Self {
+        Self {
+            task_id: task.task_id.clone(),
+            job_id: task.job_id.clone(),
+            task_type: task.task_type.clone(),
+            status: task.status.clone(),
+            file_size: task.file_size,
+            transferred_bytes: task.transferred_bytes,
+            completed_parts: task.completed_parts.len() as i32,
+            total_parts: task.total_parts,
+            retry_count: task.retry_count,
+            error_message: task.error_message.clone(),
+            active_ms: task.active_ms,
+            average_speed: task.average_speed,
+            speed: task.speed,
+            eta_seconds: task.eta_seconds,
+        }
+    }
+}
+
+/// Aggregate throughput of the running tasks, emitted every second as the
+/// `transfer-stats` event
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TransferStats {
+    pub upload_speed: f64,
+    pub download_speed: f64,
+    pub running_tasks: usize,
+    pub remaining_bytes: i64,
+    pub eta_seconds: Option<i64>,
+    pub tasks: Vec<TaskThroughput>,
+}
+
+/// Live statistics of one running task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskThroughput {
+    pub task_id: String,
+    pub transferred_bytes: i64,
+    pub active_ms: i64,
+    pub average_speed: f64,
+    pub speed: f64,
+    pub eta_seconds: Option<i64>,
+}
+
+/// A folder transfer: one child task per file, with progress aggregated
+/// from the children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferJob {
+    pub job_id: String,
+    pub job_type: TaskType,
+    pub name: String,
+    pub local_path: String,
+    pub bucket_name: String,
+    pub prefix: String,
+    pub created_at: DateTime<Utc>,
+    pub file_count: i64,
+    pub completed_count: i64,
+    pub failed_count: i64,
+    pub total_bytes: i64,
+    pub transferred_bytes: i64,
+    pub status: TaskStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -64,6 +371,58 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Cancelled,
+    /// Finished, but the data failed verification
+    Corrupted,
+    /// Was running when the app quit; its progress is checked against the
+    /// server before it can continue
+    Interrupted,
+    /// Not transferred because the conflict policy kept the existing target
+    Skipped,
+}
+
+/// What a transfer does when its target (local file or object key) exists
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    /// Transfer under a free name with a numeric suffix, e.g. `a (1).txt`
+    KeepBoth,
+    /// Replace the target only if the source was modified after it
+    OverwriteIfNewer,
+}
+
+/// How a transfer is queued. Fields the frontend leaves out take their
+/// defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransferOptions {
+    #[serde(default)]
+    pub use_encryption: bool,
+    /// SSE-C key; with `use_encryption` and no key the stored key is used
+    #[serde(default)]
+    pub encryption_key: Option<String>,
+    /// Upload part size in bytes; derived from the file size when unset
+    #[serde(default)]
+    pub part_size: Option<i64>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Object version a download fetches; `None` is the latest
+    #[serde(default)]
+    pub version_id: Option<String>,
+}
+
+/// Identifies the contents of an upload's source file, so a resumed upload
+/// can tell whether the file was edited since its first parts went up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SourceFingerprint {
+    pub size: i64,
+    /// Modification time in milliseconds since the Unix epoch
+    pub modified_ms: Option<i64>,
+    /// Hex MD5 over samples from the start, middle and end of the file
+    pub sample_md5: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +430,9 @@ pub struct CompletedPart {
     pub part_number: i32,
     pub etag: String,
     pub size: i64,
+    /// Hex MD5 of an uploaded part, used to compute the expected object ETag
+    #[serde(default)]
+    pub md5: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,10 +444,54 @@ pub struct EncryptionKey {
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub configured: bool,
+    pub unlocked: bool,
+    pub idle_timeout_secs: u64,
+}
+
+/// Limits applied by the transfer scheduler
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferSettings {
+    pub max_concurrent_tasks: usize,
+    pub max_concurrent_parts: usize,
+    /// Attempts per part or range before the task fails
+    #[serde(default = "default_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Global bandwidth limits in bytes per second; `None` is unlimited
+    #[serde(default)]
+    pub upload_limit: Option<u64>,
+    #[serde(default)]
+    pub download_limit: Option<u64>,
+    /// Queue interrupted tasks again after startup instead of leaving them paused
+    #[serde(default)]
+    pub resume_interrupted: bool,
+}
+
+fn default_retry_attempts() -> u32 {
+    5
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_tasks: 3,
+            max_concurrent_parts: 4,
+            retry_attempts: default_retry_attempts(),
+            upload_limit: None,
+            download_limit: None,
+            resume_interrupted: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamToken {
     pub token: String,
     pub bucket: String,
     pub object_key: String,
+    #[serde(default)]
+    pub version_id: Option<String>,
     pub expires_at: DateTime<Utc>,
 }
@@ -0,0 +1,198 @@
+// Retry policy for individual transfer operations (one part, one range).
+// Failures are classified where they happen: retryable ones are reported as
+// `AppError::Transient`, everything else fails the operation immediately.
+
+use std::future::Future;
+use std::time::Duration;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use rand::Rng;
+use crate::error::{AppError, Result};
+
+/// S3 error codes worth another attempt even without a 5xx status
+const RETRYABLE_CODES: &[&str] = &[
+    "SlowDown",
+    "RequestTimeout",
+    "RequestTimeTooSkewed",
+    "InternalError",
+    "ServiceUnavailable",
+    "XMinioServerNotInitialized",
+    // Content-MD5 mismatch: the part was corrupted in transit; resending
+    // the buffered data usually succeeds
+    "BadDigest",
+];
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(max_attempts: u32) -> Self {
+        Self { max_attempts: max_attempts.max(1), ..Self::default() }
+    }
+
+    /// Delay before retry number `attempt` (1-based): exponential growth
+    /// capped at `max_delay`, with the upper half jittered so concurrent
+    /// parts don't retry in lockstep
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << (attempt.saturating_sub(1)).min(16));
+        let capped = exp.min(self.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+
+    /// Run `op` until it succeeds, fails with a non-retryable error or runs
+    /// out of attempts. `on_retry` is called before each new attempt.
+    pub async fn run<T, F, Fut>(&self, what: &str, on_retry: impl Fn(), mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Err(e) if e.is_retryable() && attempt < self.max_attempts => {
+                    let delay = self.backoff(attempt);
+                    eprintln!(
+                        "[Retry] {} failed (attempt {}/{}): {}; retrying in {:?}",
+                        what, attempt, self.max_attempts, e, delay
+                    );
+                    on_retry();
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Classify an S3 SDK error. Timeouts, dropped connections, throttling and
+/// server errors are transient; access, missing bucket/key, certificate and
+/// other client errors are not.
+pub fn classify_sdk_error<E>(e: SdkError<E, HttpResponse>) -> AppError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let retryable = match &e {
+        SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+        SdkError::DispatchFailure(failure) => {
+            (failure.is_io() || failure.is_timeout()) && !is_tls_failure(&e)
+        }
+        SdkError::ServiceError(service) => {
+            let status = service.raw().status().as_u16();
+            status >= 500
+                || status == 429
+                || service.err().code().is_some_and(|code| RETRYABLE_CODES.contains(&code))
+        }
+        _ => false,
+    };
+
+    let message = DisplayErrorContext(&e).to_string();
+    if retryable {
+        AppError::Transient(message)
+    } else {
+        AppError::S3(message)
+    }
+}
+
+/// Classify a failed plain HTTP request (presigned range downloads)
+pub fn classify_http_error(e: &reqwest::Error, message: String) -> AppError {
+    if is_tls_failure(e) {
+        AppError::Other(message)
+    } else if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() {
+        AppError::Transient(message)
+    } else {
+        AppError::Other(message)
+    }
+}
+
+/// Whether a failure was the TLS handshake rejecting the server: an untrusted
+/// chain or a pin mismatch won't change on retry. rustls errors reach us
+/// wrapped in `io::Error`, whose `source()` skips the wrapped error.
+fn is_tls_failure(e: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(e);
+    while let Some(err) = current {
+        if err.downcast_ref::<rustls::Error>().is_some() {
+            return true;
+        }
+        let wrapped = err.downcast_ref::<std::io::Error>().and_then(|io| io.get_ref());
+        if wrapped.is_some_and(|inner| inner.downcast_ref::<rustls::Error>().is_some()) {
+            return true;
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Whether an HTTP status is worth retrying
+pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..=10 {
+            let delay = policy.backoff(attempt);
+            let ceiling = policy.base_delay.saturating_mul(1 << (attempt - 1)).min(policy.max_delay);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+        }
+        assert!(policy.backoff(100) <= policy.max_delay);
+    }
+
+    #[test]
+    fn test_tls_failures_are_detected_through_io_errors() {
+        let rejected = rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer);
+        let io = std::io::Error::new(std::io::ErrorKind::InvalidData, rejected);
+        assert!(is_tls_failure(&io));
+
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset");
+        assert!(!is_tls_failure(&reset));
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_errors_only() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        };
+
+        let calls = AtomicU32::new(0);
+        let retries = AtomicU32::new(0);
+        let result: Result<()> = policy.run("op", || { retries.fetch_add(1, Ordering::SeqCst); }, || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(AppError::Transient("reset".to_string())) }
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(retries.load(Ordering::SeqCst), 2);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = policy.run("op", || {}, || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(AppError::S3("AccessDenied".to_string())) }
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
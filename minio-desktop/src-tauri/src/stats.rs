@@ -0,0 +1,116 @@
+// Throughput measurement for transfers: a moving-window speed per task,
+// from which the ETA and the global aggregate are derived.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Speeds are averaged over this much recent history
+pub const SPEED_WINDOW: Duration = Duration::from_secs(10);
+
+/// Bytes transferred over a sliding time window
+pub struct ThroughputMeter {
+    window: Duration,
+    started: Instant,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ThroughputMeter {
+    pub fn new(window: Duration) -> Self {
+        Self::starting_at(window, Instant::now())
+    }
+
+    fn starting_at(window: Duration, started: Instant) -> Self {
+        Self { window, started, samples: VecDeque::new() }
+    }
+
+    pub fn record(&mut self, bytes: u64) {
+        self.record_at(Instant::now(), bytes);
+    }
+
+    fn record_at(&mut self, now: Instant, bytes: u64) {
+        self.samples.push_back((now, bytes));
+        self.prune(now);
+    }
+
+    /// Current speed in bytes per second
+    pub fn speed(&self) -> f64 {
+        self.speed_at(Instant::now())
+    }
+
+    /// Bytes inside the window divided by the window length, or by the time
+    /// since the meter started while that is shorter, so a fresh transfer
+    /// isn't reported as slow
+    fn speed_at(&self, now: Instant) -> f64 {
+        let span = now.duration_since(self.started).min(self.window);
+        // Avoid absurd figures from a single sample in the first instant
+        let span = span.max(Duration::from_secs(1)).as_secs_f64();
+        let bytes: u64 = self.samples
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= self.window)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        bytes as f64 / span
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) <= self.window {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// Seconds left at `speed` bytes per second; unknown while nothing moves
+pub fn eta_seconds(remaining_bytes: i64, speed: f64) -> Option<i64> {
+    if remaining_bytes <= 0 {
+        return Some(0);
+    }
+    if speed < 1.0 {
+        return None;
+    }
+    Some((remaining_bytes as f64 / speed).ceil() as i64)
+}
+
+/// Bytes per second over the time a task has actually been running
+pub fn average_speed(bytes: i64, active_ms: i64) -> f64 {
+    if active_ms <= 0 {
+        return 0.0;
+    }
+    bytes as f64 * 1000.0 / active_ms as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_uses_elapsed_time_until_window_fills() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::starting_at(Duration::from_secs(10), start);
+        meter.record_at(start + Duration::from_secs(1), 1000);
+        meter.record_at(start + Duration::from_secs(2), 1000);
+        assert_eq!(meter.speed_at(start + Duration::from_secs(2)), 1000.0);
+    }
+
+    #[test]
+    fn test_old_samples_leave_the_window() {
+        let start = Instant::now();
+        let mut meter = ThroughputMeter::starting_at(Duration::from_secs(10), start);
+        meter.record_at(start + Duration::from_secs(1), 10_000);
+        meter.record_at(start + Duration::from_secs(15), 5_000);
+        assert_eq!(meter.speed_at(start + Duration::from_secs(15)), 500.0);
+        // A stalled transfer decays to zero
+        assert_eq!(meter.speed_at(start + Duration::from_secs(30)), 0.0);
+    }
+
+    #[test]
+    fn test_eta_and_average() {
+        assert_eq!(eta_seconds(1000, 300.0), Some(4));
+        assert_eq!(eta_seconds(1000, 0.0), None);
+        assert_eq!(eta_seconds(0, 0.0), Some(0));
+        assert_eq!(average_speed(5000, 2000), 2500.0);
+        assert_eq!(average_speed(5000, 0), 0.0);
+    }
+}
@@ -97,7 +97,7 @@ impl StreamServer {
         self.port
     }
     
-    pub async fn create_token(&self, bucket: String, object_key: String) -> String {
+    pub async fn create_token(&self, bucket: String, object_key: String, version_id: Option<String>) -> String {
         let token = uuid::Uuid::new_v4().to_string();
         let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
         
@@ -105,6 +105,7 @@ impl StreamServer {
             token: token.clone(),
             bucket,
             object_key,
+            version_id,
             expires_at,
         };
         
@@ -200,7 +201,8 @@ async fn stream_handler(
         .get_client()
         .get_object()
         .bucket(&stream_token.bucket)
-        .key(&stream_token.object_key);
+        .key(&stream_token.object_key)
+        .set_version_id(stream_token.version_id.clone());
 
     // Add SSE-C headers if encryption key exists
     if let Some(ref key) = encryption_key {
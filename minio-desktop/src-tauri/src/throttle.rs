@@ -0,0 +1,194 @@
+// Token-bucket bandwidth limiting for transfers. Limiters can be changed at
+// runtime; bytes already in flight just finish at the old pace.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+/// Bodies are released to the network in slices of this size
+const SLICE_SIZE: usize = 64 * 1024;
+
+/// Token bucket holding at most one second's worth of bytes. `None` means
+/// unlimited.
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    rate: Option<u64>,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: Option<u64>) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                rate,
+                tokens: rate.unwrap_or(0) as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn rate(&self) -> Option<u64> {
+        self.bucket.lock().unwrap().rate
+    }
+
+    pub fn set_rate(&self, rate: Option<u64>) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.rate = rate.filter(|r| *r > 0);
+        // Drop any debt or burst built up under the old rate
+        bucket.tokens = bucket.tokens.clamp(0.0, bucket.rate.unwrap_or(0) as f64);
+        bucket.updated = Instant::now();
+    }
+
+    /// Take `bytes` tokens and return how long the caller must wait before
+    /// sending them. The bucket may go into debt, which later callers repay.
+    fn reserve(&self, bytes: usize) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let Some(rate) = bucket.rate else {
+            return Duration::ZERO;
+        };
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.updated = now;
+        bucket.tokens = (bucket.tokens + elapsed * rate as f64).min(rate as f64);
+        bucket.tokens -= bytes as f64;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate as f64)
+        }
+    }
+}
+
+/// The set of limiters a transfer is subject to (e.g. the global upload
+/// limit and the task's own limit); the slowest one wins.
+#[derive(Clone, Default)]
+pub struct Throttle {
+    limiters: Vec<Arc<RateLimiter>>,
+}
+
+impl Throttle {
+    pub fn new(limiters: Vec<Arc<RateLimiter>>) -> Self {
+        Self { limiters }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.limiters.iter().any(|l| l.rate().is_some())
+    }
+
+    fn reserve(&self, bytes: usize) -> Duration {
+        self.limiters
+            .iter()
+            .map(|l| l.reserve(bytes))
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.reserve(bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// In-memory request body that releases its data at the throttle's pace
+pub struct ThrottledBody {
+    data: Bytes,
+    throttle: Throttle,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl ThrottledBody {
+    pub fn new(data: Bytes, throttle: Throttle) -> Self {
+        Self { data, throttle, sleep: None }
+    }
+}
+
+impl Body for ThrottledBody {
+    type Data = Bytes;
+    type Error = std::convert::Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Bytes>, Self::Error>>> {
+        if let Some(sleep) = self.sleep.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.sleep = None;
+        }
+
+        if self.data.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        let len = self.data.len().min(SLICE_SIZE);
+        let slice = self.data.split_to(len);
+        let wait = self.throttle.reserve(len);
+        if !wait.is_zero() {
+            // Pay for this slice before the next one goes out
+            self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+        }
+        Poll::Ready(Some(Ok(Frame::data(slice))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.data.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_never_waits() {
+        let limiter = RateLimiter::new(None);
+        assert_eq!(limiter.reserve(usize::MAX / 2), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_debt_is_paid_at_the_configured_rate() {
+        let limiter = RateLimiter::new(Some(1000));
+        // The initial burst covers one second
+        assert_eq!(limiter.reserve(1000), Duration::ZERO);
+        let wait = limiter.reserve(500);
+        assert!(wait > Duration::from_millis(450) && wait <= Duration::from_millis(500), "{:?}", wait);
+    }
+
+    #[test]
+    fn test_rate_can_change_at_runtime() {
+        let limiter = RateLimiter::new(Some(1000));
+        limiter.reserve(5000);
+        limiter.set_rate(None);
+        assert_eq!(limiter.reserve(1_000_000), Duration::ZERO);
+        limiter.set_rate(Some(2000));
+        assert_eq!(limiter.rate(), Some(2000));
+    }
+
+    #[test]
+    fn test_throttle_uses_slowest_limiter() {
+        let fast = Arc::new(RateLimiter::new(Some(10_000)));
+        let slow = Arc::new(RateLimiter::new(Some(100)));
+        let throttle = Throttle::new(vec![fast, slow]);
+        throttle.reserve(100);
+        let wait = throttle.reserve(100);
+        assert!(wait > Duration::from_millis(900), "{:?}", wait);
+    }
+}
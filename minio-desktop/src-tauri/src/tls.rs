@@ -0,0 +1,211 @@
+// TLS trust configuration shared by the S3 client, the range downloader
+// and the stream server (which goes through the S3 client).
+
+use std::sync::Arc;
+use std::time::SystemTime;
+use rustls::client::{ServerCertVerifier, ServerCertVerified, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName, Error as RustlsError};
+use sha2::{Sha256, Digest};
+use crate::error::{AppError, Result};
+use crate::models::{TlsMode, TlsPolicy};
+
+/// Build a rustls client configuration for the given policy
+pub fn build_client_config(policy: &TlsPolicy) -> Result<ClientConfig> {
+    let verifier: Arc<dyn ServerCertVerifier> = match policy.mode {
+        TlsMode::System => Arc::new(ChainVerifier::new(load_system_roots()?)),
+        TlsMode::CustomCa => {
+            let pem = policy.ca_bundle_pem.as_deref()
+                .filter(|p| !p.trim().is_empty())
+                .ok_or_else(|| AppError::InvalidConfig("Custom CA mode requires a PEM CA bundle".to_string()))?;
+            let mut roots = load_system_roots()?;
+            add_pem_bundle(&mut roots, pem)?;
+            Arc::new(ChainVerifier::new(roots))
+        }
+        TlsMode::Pinned => {
+            let pins = policy.pinned_sha256.iter()
+                .map(|p| normalize_fingerprint(p))
+                .collect::<Result<Vec<_>>>()?;
+            if pins.is_empty() {
+                return Err(AppError::InvalidConfig("Pinned mode requires at least one SHA-256 fingerprint".to_string()));
+            }
+            Arc::new(PinnedVerifier { pins })
+        }
+        TlsMode::Insecure => Arc::new(NoCertificateVerification),
+    };
+
+    Ok(ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth())
+}
+
+/// SHA-256 fingerprint of a DER certificate, formatted as colon-separated hex
+pub fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn load_system_roots() -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs()
+        .map_err(|e| AppError::InvalidConfig(format!("Failed to load system root certificates: {}", e)))?;
+    let ders: Vec<Vec<u8>> = native.into_iter().map(|c| c.0).collect();
+    let (added, ignored) = roots.add_parsable_certificates(&ders);
+    if ignored > 0 {
+        eprintln!("[TLS] Loaded {} system root certificates ({} unparsable ignored)", added, ignored);
+    }
+    Ok(roots)
+}
+
+fn add_pem_bundle(roots: &mut RootCertStore, pem: &str) -> Result<()> {
+    let mut reader = std::io::BufReader::new(pem.as_bytes());
+    let certs = rustls_pemfile::certs(&mut reader)
+        .map_err(|e| AppError::InvalidConfig(format!("Invalid PEM CA bundle: {}", e)))?;
+    if certs.is_empty() {
+        return Err(AppError::InvalidConfig("PEM CA bundle contains no certificates".to_string()));
+    }
+    for der in certs {
+        roots.add(&Certificate(der))
+            .map_err(|e| AppError::InvalidConfig(format!("Invalid CA certificate in bundle: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Accepts "AB:CD:..." or plain hex, returns lowercase hex without separators
+fn normalize_fingerprint(pin: &str) -> Result<String> {
+    let hex: String = pin.chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidConfig(format!("Invalid SHA-256 fingerprint: {}", pin)));
+    }
+    Ok(hex)
+}
+
+/// Describe the presented chain so verification failures are actionable
+fn describe_chain(end_entity: &Certificate, intermediates: &[Certificate]) -> String {
+    std::iter::once(end_entity)
+        .chain(intermediates.iter())
+        .enumerate()
+        .map(|(i, cert)| format!("[{}] sha256={}", i, fingerprint(&cert.0)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Standard webpki chain validation with the presented chain in the error message
+struct ChainVerifier {
+    inner: WebPkiVerifier,
+}
+
+impl ChainVerifier {
+    fn new(roots: RootCertStore) -> Self {
+        Self { inner: WebPkiVerifier::new(roots, None) }
+    }
+}
+
+impl ServerCertVerifier for ChainVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, RustlsError> {
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)
+            .map_err(|e| {
+                let message = format!(
+                    "certificate verification failed for {:?}: {}; presented chain: {}",
+                    server_name, e, describe_chain(end_entity, intermediates)
+                );
+                eprintln!("[TLS] {}", message);
+                RustlsError::General(message)
+            })
+    }
+}
+
+// Accepts only a leaf certificate whose SHA-256 matches one of the pins
+struct PinnedVerifier {
+    pins: Vec<String>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, RustlsError> {
+        let leaf = fingerprint(&end_entity.0).replace(':', "").to_lowercase();
+        if self.pins.contains(&leaf) {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        let message = format!(
+            "leaf certificate does not match any pinned SHA-256 fingerprint; presented chain: {}",
+            describe_chain(end_entity, intermediates)
+        );
+        eprintln!("[TLS] {}", message);
+        Err(RustlsError::General(message))
+    }
+}
+
+// Custom certificate verifier that accepts all certificates (including self-signed).
+// Only used when the connection explicitly opts into insecure mode.
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> std::result::Result<ServerCertVerified, RustlsError> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        let colon = "AB:".repeat(31) + "AB";
+        assert_eq!(normalize_fingerprint(&colon).unwrap(), "ab".repeat(32));
+        assert!(normalize_fingerprint("abcd").is_err());
+        assert!(normalize_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_pinned_mode_requires_pins() {
+        let policy = TlsPolicy {
+            mode: TlsMode::Pinned,
+            ca_bundle_pem: None,
+            pinned_sha256: vec![],
+        };
+        assert!(build_client_config(&policy).is_err());
+    }
+
+    #[test]
+    fn test_custom_ca_requires_bundle() {
+        let policy = TlsPolicy {
+            mode: TlsMode::CustomCa,
+            ca_bundle_pem: Some("not a pem".to_string()),
+            pinned_sha256: vec![],
+        };
+        assert!(build_client_config(&policy).is_err());
+    }
+}
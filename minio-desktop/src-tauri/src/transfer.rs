@@ -1,174 +1,1401 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::fs::File;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, AsyncSeekExt};
-use tokio::sync::{Mutex, Semaphore};
-use aws_sdk_s3::primitives::ByteStream;
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use aws_sdk_s3::primitives::{ByteStream, SdkBody};
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::operation::head_object::HeadObjectOutput;
 use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use tauri::{AppHandle, Manager};
 use crate::error::{AppError, Result};
-use crate::models::{TransferTask, TaskType, TaskStatus, CompletedPart as ModelCompletedPart};
-use crate::minio::MinioClient;
+use crate::models::{ConflictPolicy, MultipartUploadInfo, TransferOptions, TransferTask, TransferJob, TransferProgress, TransferSettings, TransferStats, TaskThroughput, TaskType, TaskStatus, CompletedPart as ModelCompletedPart};
+use crate::minio::{ListOptions, MinioClient};
 use crate::db::Database;
-use chrono::Utc;
+use crate::encryption::SseCEncryption;
+use crate::conflict;
+use crate::folder;
+use crate::integrity::{self, EtagKind};
+use crate::retry::{self, RetryPolicy};
+use crate::stats::{self, ThroughputMeter};
+use crate::throttle::{RateLimiter, Throttle, ThrottledBody};
+use chrono::{DateTime, Utc};
+
+const MIB: i64 = 1024 * 1024;
+/// S3 multipart limits: parts are 5 MiB to 5 GiB, at most 10,000 per upload
+const MIN_PART_SIZE: i64 = 5 * MIB;
+const MAX_PART_SIZE: i64 = 5 * 1024 * MIB;
+const MAX_PARTS: i64 = 10_000;
+/// Default part sizes grow to keep uploads near this many requests...
+const TARGET_PARTS: i64 = 1_000;
+/// ...up to this size, beyond which only the part limit pushes them higher
+const DEFAULT_MAX_PART_SIZE: i64 = 64 * MIB;
+/// Part progress is written to the database at most this often; the UI
+/// gets every part through events
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Why a running task was asked to stop
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopReason {
+    Pause,
+    Cancel,
+}
+
+/// Throughput bookkeeping for the current run of a task
+struct TaskStats {
+    task_type: TaskType,
+    meter: ThroughputMeter,
+    started: Instant,
+    /// Active time accumulated by earlier runs
+    previous_active_ms: i64,
+    file_size: i64,
+    transferred_bytes: i64,
+}
+
+impl TaskStats {
+    fn active_ms(&self) -> i64 {
+        self.previous_active_ms + self.started.elapsed().as_millis() as i64
+    }
+
+    fn throughput(&self, task_id: &str) -> TaskThroughput {
+        let speed = self.meter.speed();
+        let active_ms = self.active_ms();
+        TaskThroughput {
+            task_id: task_id.to_string(),
+            transferred_bytes: self.transferred_bytes,
+            active_ms,
+            average_speed: stats::average_speed(self.transferred_bytes, active_ms),
+            speed,
+            eta_seconds: stats::eta_seconds(self.file_size - self.transferred_bytes, speed),
+        }
+    }
+}
+
+/// Control handle for a running task. Stopping cancels the token, which
+/// interrupts any in-flight request of the task.
+#[derive(Clone)]
+struct TaskControl {
+    token: CancellationToken,
+    reason: Arc<std::sync::Mutex<Option<StopReason>>>,
+    /// Retries since the count was last written to the task
+    retries: Arc<AtomicI32>,
+    /// The task's own bandwidth limit, applied on top of the global one
+    limiter: Arc<RateLimiter>,
+    last_checkpoint: Arc<std::sync::Mutex<Instant>>,
+    stats: Arc<std::sync::Mutex<TaskStats>>,
+}
+
+impl TaskControl {
+    fn new(task: &TransferTask) -> Self {
+        Self {
+            token: CancellationToken::new(),
+            reason: Arc::new(std::sync::Mutex::new(None)),
+            retries: Arc::new(AtomicI32::new(0)),
+            limiter: Arc::new(RateLimiter::new(task.bandwidth_limit.map(|l| l as u64))),
+            last_checkpoint: Arc::new(std::sync::Mutex::new(Instant::now())),
+            stats: Arc::new(std::sync::Mutex::new(TaskStats {
+                task_type: task.task_type.clone(),
+                meter: ThroughputMeter::new(stats::SPEED_WINDOW),
+                started: Instant::now(),
+                previous_active_ms: task.active_ms,
+                file_size: task.file_size,
+                transferred_bytes: task.transferred_bytes,
+            })),
+        }
+    }
+
+    /// Count `bytes` just transferred by the task and refresh its statistics
+    fn record_progress(&self, task: &mut TransferTask, bytes: i64) {
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.meter.record(bytes.max(0) as u64);
+            stats.file_size = task.file_size;
+            stats.transferred_bytes = task.transferred_bytes;
+        }
+        self.refresh_stats(task);
+    }
+
+    /// Copy the current speed, ETA and active time onto the task
+    fn refresh_stats(&self, task: &mut TransferTask) {
+        let stats = self.stats.lock().unwrap();
+        let speed = stats.meter.speed();
+        task.active_ms = stats.active_ms();
+        task.average_speed = stats::average_speed(task.transferred_bytes, task.active_ms);
+        task.speed = speed;
+        task.eta_seconds = stats::eta_seconds(task.file_size - task.transferred_bytes, speed);
+    }
+
+    /// Overlay the live progress of a running task on its last checkpoint
+    fn apply_live(&self, task: &mut TransferTask) {
+        {
+            let stats = self.stats.lock().unwrap();
+            task.file_size = stats.file_size;
+            task.transferred_bytes = stats.transferred_bytes;
+        }
+        self.refresh_stats(task);
+    }
+
+    /// Whether enough time has passed to persist progress again
+    fn checkpoint_due(&self) -> bool {
+        let mut last = self.last_checkpoint.lock().unwrap();
+        if last.elapsed() < CHECKPOINT_INTERVAL {
+            return false;
+        }
+        *last = Instant::now();
+        true
+    }
+
+    fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Move the retries counted so far onto the task record
+    fn flush_retries(&self, task: &mut TransferTask) {
+        task.retry_count += self.retries.swap(0, Ordering::Relaxed);
+    }
+
+    fn stop(&self, reason: StopReason) {
+        *self.reason.lock().unwrap() = Some(reason);
+        self.token.cancel();
+    }
+
+    fn reason(&self) -> Option<StopReason> {
+        *self.reason.lock().unwrap()
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.token.is_cancelled() {
+            return Err(AppError::TaskStopped);
+        }
+        Ok(())
+    }
+
+    /// Run a future, abandoning it as soon as the task is stopped
+    async fn run<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::select! {
+            _ = self.token.cancelled() => Err(AppError::TaskStopped),
+            result = fut => result,
+        }
+    }
+}
+
+pub struct TransferManager {
+    app_handle: AppHandle,
+    db: Arc<Mutex<Database>>,
+    minio_client: Arc<Mutex<Option<MinioClient>>>,
+    /// Profile the current client was built from. Written under the client
+    /// lock, so the pair is always read consistently.
+    active_profile: Arc<Mutex<Option<String>>>,
+    controls: Mutex<HashMap<String, TaskControl>>,
+    /// SSE-C keys of this session's encrypted tasks, kept until they
+    /// complete so pausing and resuming doesn't need the key store
+    task_keys: Mutex<HashMap<String, SseCEncryption>>,
+    settings: Mutex<TransferSettings>,
+    upload_limiter: Arc<RateLimiter>,
+    download_limiter: Arc<RateLimiter>,
+    /// Serializes scheduler passes so a slot is never handed out twice
+    dispatch_lock: Mutex<()>,
+    /// Profiles whose tasks interrupted in an earlier session have been
+    /// reconciled; each profile's tasks can only be checked against its server
+    reconciled_profiles: Mutex<HashSet<String>>,
+}
+
+impl TransferManager {
+    pub fn new(
+        app_handle: AppHandle,
+        db: Arc<Mutex<Database>>,
+        minio_client: Arc<Mutex<Option<MinioClient>>>,
+        active_profile: Arc<Mutex<Option<String>>>,
+        settings: TransferSettings,
+    ) -> Self {
+        Self {
+            app_handle,
+            db,
+            minio_client,
+            active_profile,
+            controls: Mutex::new(HashMap::new()),
+            task_keys: Mutex::new(HashMap::new()),
+            upload_limiter: Arc::new(RateLimiter::new(settings.upload_limit)),
+            download_limiter: Arc::new(RateLimiter::new(settings.download_limit)),
+            settings: Mutex::new(settings),
+            dispatch_lock: Mutex::new(()),
+            reconciled_profiles: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Snapshot of the current client so transfers don't hold the client lock
+    async fn client(&self) -> Result<MinioClient> {
+        let client_guard = self.minio_client.lock().await;
+        client_guard.clone().ok_or(AppError::NotConnected)
+    }
+
+    /// Snapshot of the current client together with the profile it belongs to
+    async fn connection(&self) -> Result<(MinioClient, Option<String>)> {
+        let client_guard = self.minio_client.lock().await;
+        let client = client_guard.clone().ok_or(AppError::NotConnected)?;
+        let profile_id = self.active_profile.lock().await.clone();
+        Ok((client, profile_id))
+    }
+
+    async fn save(&self, task: &TransferTask) -> Result<()> {
+        let db = self.db.lock().await;
+        db.save_task(task)
+    }
+
+    /// Tell the UI about a task's progress or status
+    fn emit_progress(&self, task: &TransferTask) {
+        if let Err(e) = self.app_handle.emit_all("transfer-progress", TransferProgress::from(task)) {
+            eprintln!("Failed to emit progress for task {}: {}", task.task_id, e);
+        }
+    }
+
+    /// Report part progress, persisting it only every `CHECKPOINT_INTERVAL`.
+    /// Parts finished since the last checkpoint are redone after a crash.
+    async fn checkpoint(&self, task: &TransferTask, control: &TaskControl) -> Result<()> {
+        self.emit_progress(task);
+        if control.checkpoint_due() {
+            self.save(task).await?;
+        }
+        Ok(())
+    }
+
+    async fn register(&self, task: &TransferTask) -> TaskControl {
+        let control = TaskControl::new(task);
+        self.controls.lock().await.insert(task.task_id.clone(), control.clone());
+        control
+    }
+
+    /// Limiters that apply to a task: the global one for its direction and its own
+    fn throttle(&self, task: &TransferTask, control: &TaskControl) -> Throttle {
+        let global = match task.task_type {
+            TaskType::Upload => self.upload_limiter.clone(),
+            TaskType::Download => self.download_limiter.clone(),
+        };
+        Throttle::new(vec![global, control.limiter.clone()])
+    }
+
+    async fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::with_max_attempts(self.settings.lock().await.retry_attempts)
+    }
+
+    /// SSE-C key for a new task: the one supplied, or the stored key when
+    /// encryption is requested without one
+    pub async fn resolve_key(&self, use_encryption: bool, encryption_key: Option<&str>) -> Result<Option<SseCEncryption>> {
+        match encryption_key {
+            Some(key_base64) => Ok(Some(SseCEncryption::from_base64(key_base64)?)),
+            None if use_encryption => Ok(Some(self.stored_key().await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn stored_key(&self) -> Result<SseCEncryption> {
+        let key = {
+            let db = self.db.lock().await;
+            db.get_encryption_key()?
+        };
+        let key = key.ok_or_else(|| AppError::Encryption("No encryption key available for this transfer".to_string()))?;
+        SseCEncryption::from_base64(&key.key_value)
+    }
+
+    /// Record which key a new task is encrypted with. Every later request
+    /// of the task, including after a restart, must use the same key.
+    async fn bind_key(&self, task: &mut TransferTask, sse_c: Option<SseCEncryption>) {
+        task.use_encryption = sse_c.is_some();
+        task.encryption_key_md5 = sse_c.as_ref().map(|k| k.get_key_md5().to_string());
+        if let Some(sse_c) = sse_c {
+            self.task_keys.lock().await.insert(task.task_id.clone(), sse_c);
+        }
+    }
+
+    /// The key an encrypted task was started with: from this session, or
+    /// looked up in the key store by its MD5. Never another key, since the
+    /// server rejects parts and ranges under a different key.
+    async fn task_key(&self, task: &TransferTask) -> Result<Option<SseCEncryption>> {
+        if !task.use_encryption {
+            return Ok(None);
+        }
+        if let Some(sse_c) = self.task_keys.lock().await.get(&task.task_id) {
+            return Ok(Some(sse_c.clone()));
+        }
+
+        let key_md5 = task.encryption_key_md5.as_deref().ok_or_else(|| AppError::Encryption(
+            "This task was queued without a record of its encryption key; restart the transfer".to_string()
+        ))?;
+        let key = {
+            let db = self.db.lock().await;
+            db.get_encryption_key_by_md5(key_md5)?
+        };
+        let key = key.ok_or_else(|| AppError::Encryption(format!(
+            "The encryption key this task was started with (MD5 {}) is no longer in the key store", key_md5
+        )))?;
+        Ok(Some(SseCEncryption::from_base64(&key.key_value)?))
+    }
+
+    /// Queue an upload task. It starts once the scheduler has a free slot.
+    pub async fn enqueue_upload(
+        self: &Arc<Self>,
+        local_path: String,
+        bucket: String,
+        object_key: String,
+        options: TransferOptions,
+    ) -> Result<String> {
+        let sse_c = self.resolve_key(options.use_encryption, options.encryption_key.as_deref()).await?;
+
+        let task_id = self.queue_upload(local_path, bucket, object_key, sse_c, &options, None).await?;
+        self.schedule();
+
+        Ok(task_id)
+    }
+
+    /// Queue one upload task per file below `local_dir`, grouped under a job.
+    /// Keys are the files' relative paths under `prefix`.
+    pub async fn enqueue_folder_upload(
+        self: &Arc<Self>,
+        local_dir: String,
+        bucket: String,
+        prefix: String,
+        follow_symlinks: bool,
+        options: TransferOptions,
+    ) -> Result<String> {
+        let sse_c = self.resolve_key(options.use_encryption, options.encryption_key.as_deref()).await?;
+
+        let root = PathBuf::from(&local_dir);
+        let files = {
+            let root = root.clone();
+            tokio::task::spawn_blocking(move || folder::collect_files(&root, follow_symlinks))
+                .await
+                .map_err(|e| AppError::Other(e.to_string()))??
+        };
+        if files.is_empty() {
+            return Err(AppError::InvalidConfig(format!("No files found in {}", local_dir)));
+        }
+
+        let prefix = folder::normalize_prefix(&prefix);
+        let job = TransferJob {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            job_type: TaskType::Upload,
+            name: root.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("folder")
+                .to_string(),
+            local_path: local_dir,
+            bucket_name: bucket.clone(),
+            prefix: prefix.clone(),
+            created_at: Utc::now(),
+            file_count: files.len() as i64,
+            completed_count: 0,
+            failed_count: 0,
+            total_bytes: 0,
+            transferred_bytes: 0,
+            status: TaskStatus::Pending,
+        };
+        {
+            let db = self.db.lock().await;
+            db.save_job(&job)?;
+        }
+
+        println!("[Folder] Queueing {} files from {} to {}/{}", files.len(), job.local_path, bucket, prefix);
+        for file in files {
+            let object_key = folder::object_key_for(&root, &file, &prefix)?;
+            self.queue_upload(
+                file.to_string_lossy().into_owned(),
+                bucket.clone(),
+                object_key,
+                sse_c.clone(),
+                &options,
+                Some(job.job_id.clone()),
+            ).await?;
+        }
+        self.schedule();
+
+        Ok(job.job_id)
+    }
+
+    /// Queue one download task per object below `prefix`, grouped under a
+    /// job and mirroring the key hierarchy inside `local_dir`. Each object
+    /// is its own task, so one failure doesn't stop the others.
+    pub async fn enqueue_folder_download(
+        self: &Arc<Self>,
+        bucket: String,
+        prefix: String,
+        local_dir: String,
+        options: TransferOptions,
+    ) -> Result<String> {
+        let client = self.client().await?;
+        // One key applies to every object of the job; a version only
+        // identifies a single object
+        let sse_c = self.resolve_key(options.use_encryption, options.encryption_key.as_deref()).await?;
+        let options = TransferOptions { version_id: None, ..options };
+        let prefix = folder::normalize_prefix(&prefix);
+        let root = PathBuf::from(&local_dir);
+
+        // List the whole prefix page by page
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let options = ListOptions {
+                prefix: Some(prefix.as_str()).filter(|p| !p.is_empty()),
+                recursive: true,
+                continuation_token: continuation_token.as_deref(),
+                ..Default::default()
+            };
+            let page = client.list_objects_page(&bucket, &options).await?;
+            objects.extend(page.objects.into_iter().filter(|o| !o.is_dir));
+
+            match page.next_continuation_token {
+                Some(token) if page.is_truncated => continuation_token = Some(token),
+                _ => break,
+            }
+        }
+
+        let mut targets = Vec::new();
+        for object in objects {
+            match folder::local_path_for(&root, &object.key, &prefix) {
+                Ok(Some(path)) => targets.push((object, path)),
+                Ok(None) => {}
+                Err(e) => eprintln!("[Folder] Skipping {}: {}", object.key, e),
+            }
+        }
+        if targets.is_empty() {
+            return Err(AppError::InvalidConfig(format!("No objects found under {}/{}", bucket, prefix)));
+        }
+
+        let job = TransferJob {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            job_type: TaskType::Download,
+            name: prefix.trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .filter(|n| !n.is_empty())
+                .unwrap_or(&bucket)
+                .to_string(),
+            local_path: local_dir,
+            bucket_name: bucket.clone(),
+            prefix: prefix.clone(),
+            created_at: Utc::now(),
+            file_count: targets.len() as i64,
+            completed_count: 0,
+            failed_count: 0,
+            total_bytes: 0,
+            transferred_bytes: 0,
+            status: TaskStatus::Pending,
+        };
+        {
+            let db = self.db.lock().await;
+            db.save_job(&job)?;
+        }
+
+        println!("[Folder] Queueing {} objects from {}/{} to {}", targets.len(), bucket, prefix, job.local_path);
+        for (object, path) in targets {
+            let mut task = self.new_task(
+                TaskType::Download,
+                path.to_string_lossy().into_owned(),
+                bucket.clone(),
+                object.key,
+                object.size,
+                &options,
+            ).await?;
+            task.remote_etag = object.etag;
+            task.job_id = Some(job.job_id.clone());
+            self.bind_key(&mut task, sse_c.clone()).await;
+            self.save(&task).await?;
+            self.emit_progress(&task);
+        }
+        self.schedule();
+
+        Ok(job.job_id)
+    }
+
+    async fn queue_upload(
+        &self,
+        local_path: String,
+        bucket: String,
+        object_key: String,
+        sse_c: Option<SseCEncryption>,
+        options: &TransferOptions,
+        job_id: Option<String>,
+    ) -> Result<String> {
+        let file_metadata = tokio::fs::metadata(&local_path).await?;
+        let file_size = file_metadata.len() as i64;
+
+        let mut task = self.new_task(TaskType::Upload, local_path, bucket, object_key, file_size, options).await?;
+        task.job_id = job_id;
+        self.bind_key(&mut task, sse_c).await;
+        self.save(&task).await?;
+        self.emit_progress(&task);
+
+        Ok(task.task_id)
+    }
+
+    /// Queue a download task. The object is inspected now so the queue shows
+    /// its size. SSE-C encrypted objects need their key for every request,
+    /// including this HEAD. `options.version_id` selects an older version.
+    pub async fn enqueue_download(
+        self: &Arc<Self>,
+        bucket: String,
+        object_key: String,
+        local_path: String,
+        options: TransferOptions,
+    ) -> Result<String> {
+        let client = self.client().await?;
+        let sse_c = self.resolve_key(options.use_encryption, options.encryption_key.as_deref()).await?;
+
+        // Get object size
+        let head_result = self.head_target(&client, &bucket, &object_key, options.version_id.as_deref(), sse_c.as_ref()).await?
+            .ok_or_else(|| AppError::S3(format!("{}/{} does not exist", bucket, object_key)))?;
+
+        let file_size = head_result.content_length().unwrap_or(0);
+        let mut task = self.new_task(TaskType::Download, local_path, bucket, object_key, file_size, &options).await?;
+        task.remote_etag = head_result.e_tag().map(|e| e.to_string());
+        self.bind_key(&mut task, sse_c).await;
+
+        self.save(&task).await?;
+        self.emit_progress(&task);
+        self.schedule();
+
+        Ok(task.task_id)
+    }
+
+    /// A pending task with `options` applied. Uploads are named after the
+    /// local file and split into the requested part size, if any; downloads
+    /// are named after the object and fetched in chunks sized from it.
+    async fn new_task(
+        &self,
+        task_type: TaskType,
+        local_path: String,
+        bucket: String,
+        object_key: String,
+        file_size: i64,
+        options: &TransferOptions,
+    ) -> Result<TransferTask> {
+        let (name_source, part_size, version_id) = match task_type {
+            TaskType::Upload => (&local_path, upload_part_size(file_size, options.part_size)?, None),
+            TaskType::Download => (&object_key, download_chunk_size(file_size), options.version_id.clone()),
+        };
+        let file_name = Path::new(name_source)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let queue_position = {
+            let db = self.db.lock().await;
+            db.next_queue_position()?
+        };
+        let profile_id = self.active_profile.lock().await.clone();
+
+        Ok(TransferTask {
+            task_id: uuid::Uuid::new_v4().to_string(),
+            profile_id,
+            task_type,
+            file_name,
+            local_path,
+            bucket_name: bucket,
+            object_key,
+            file_size,
+            upload_id: None,
+            part_size,
+            total_parts: part_count(file_size, part_size),
+            completed_parts: vec![],
+            transferred_bytes: 0,
+            status: TaskStatus::Pending,
+            error_message: None,
+            use_encryption: false,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            completed_at: None,
+            priority: options.priority,
+            queue_position,
+            remote_etag: None,
+            job_id: None,
+            retry_count: 0,
+            bandwidth_limit: None,
+            encryption_key_md5: None,
+            conflict_policy: options.conflict_policy,
+            source_fingerprint: None,
+            version_id,
+            active_ms: 0,
+            average_speed: 0.0,
+            speed: 0.0,
+            eta_seconds: None,
+        })
+    }
+
+    /// Start queued tasks while there are free slots. Called whenever a task
+    /// is queued or finishes, the limits change or a connection is made.
+    pub fn schedule(self: &Arc<Self>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = manager.dispatch().await {
+                eprintln!("[Scheduler] Failed to start queued tasks: {}", e);
+            }
+        });
+    }
+
+    async fn dispatch(self: Arc<Self>) -> Result<()> {
+        let _guard = self.dispatch_lock.lock().await;
+
+        // Leave the queue alone until there is a connection to run it against
+        let Ok((client, profile_id)) = self.connection().await else {
+            return Ok(());
+        };
+
+        // The first pass with a profile settles tasks the last session left behind
+        if self.reconciled_profiles.lock().await.insert(profile_id.clone().unwrap_or_default()) {
+            self.reconcile_interrupted(&client, profile_id.as_deref()).await;
+        }
+
+        let max_tasks = self.settings.lock().await.max_concurrent_tasks;
+        let pending = {
+            let db = self.db.lock().await;
+            db.get_pending_tasks()?
+        };
+
+        // Tasks of other profiles wait until their profile is active again
+        for mut task in pending.into_iter().filter(|t| belongs_to(t, profile_id.as_deref())) {
+            if self.controls.lock().await.len() >= max_tasks {
+                break;
+            }
+
+            task.status = TaskStatus::Running;
+            task.error_message = None;
+            task.updated_at = Utc::now();
+            self.save(&task).await?;
+            self.emit_progress(&task);
+
+            println!("[Scheduler] Starting task {} ({})", task.task_id, task.file_name);
+            let control = self.register(&task).await;
+            let manager = self.clone();
+            let client = client.clone();
+            tokio::spawn(async move {
+                let result = manager.execute(&client, &mut task, &control).await;
+                if let Err(e) = manager.finish_task(&mut task, &control, result).await {
+                    eprintln!("Task {} failed: {}", task.task_id, e);
+                }
+                // A slot is free again
+                manager.schedule();
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Run a task on the client it was dispatched with, so switching profiles
+    /// mid-transfer can't move the rest of it to another server
+    async fn execute(&self, client: &MinioClient, task: &mut TransferTask, control: &TaskControl) -> Result<()> {
+        if task.transferred_bytes > 0 {
+            println!("Resuming task {} from {}/{} bytes", task.task_id, task.transferred_bytes, task.file_size);
+        }
+        let sse_c = self.task_key(task).await?;
+        match task.task_type {
+            TaskType::Upload => self.run_upload(client, task, control, sse_c).await,
+            TaskType::Download => self.run_download(client, task, control, sse_c.as_ref()).await,
+        }
+    }
+
+    async fn run_upload(&self, client: &MinioClient, task: &mut TransferTask, control: &TaskControl, sse_c: Option<SseCEncryption>) -> Result<()> {
+        // The target is only checked before anything has been sent
+        let fresh = task.upload_id.is_none() && task.completed_parts.is_empty();
+        if fresh {
+            self.settle_remote_target(client, task, sse_c.as_ref()).await?;
+        }
+        self.check_source_unchanged(task, control, fresh).await?;
+
+        // Only files below the minimum part size go up as a single PUT, which
+        // holds the whole file in memory; larger ones are multipart uploads,
+        // even when they fit in one part
+        if task.file_size < MIN_PART_SIZE {
+            return self.upload_small_file(client, task, control, sse_c.as_ref()).await;
+        }
+
+        if task.upload_id.is_none() {
+            self.create_multipart_upload(client, task, sse_c.as_ref()).await?;
+        }
+        self.upload_parts(client, task, control, sse_c.as_ref()).await
+    }
+
+    /// Record the outcome of a run. Stops requested through pause/cancel are
+    /// not errors for the caller.
+    async fn finish_task(&self, task: &mut TransferTask, control: &TaskControl, result: Result<()>) -> Result<()> {
+        self.controls.lock().await.remove(&task.task_id);
+        control.flush_retries(task);
+        if matches!(result, Ok(_) | Err(AppError::Skipped(_))) || control.reason() == Some(StopReason::Cancel) {
+            self.task_keys.lock().await.remove(&task.task_id);
+        }
+
+        let result = match result {
+            Ok(_) => {
+                task.status = TaskStatus::Completed;
+                task.completed_at = Some(Utc::now());
+                task.transferred_bytes = task.file_size;
+                task.error_message = None;
+                Ok(())
+            }
+            Err(AppError::Skipped(reason)) => {
+                println!("Task {} skipped: {}", task.task_id, reason);
+                task.status = TaskStatus::Skipped;
+                task.completed_at = Some(Utc::now());
+                task.error_message = Some(reason);
+                Ok(())
+            }
+            Err(AppError::TaskStopped) if control.reason() == Some(StopReason::Pause) => {
+                println!("Task {} paused at {}/{} bytes", task.task_id, task.transferred_bytes, task.file_size);
+                task.status = TaskStatus::Paused;
+                Ok(())
+            }
+            Err(AppError::TaskStopped) => {
+                println!("Task {} cancelled", task.task_id);
+                self.discard_progress(task).await;
+                task.status = TaskStatus::Cancelled;
+                Ok(())
+            }
+            Err(e @ AppError::Integrity(_)) => {
+                eprintln!("Task {} failed verification: {}", task.task_id, e);
+                // A completed multipart upload has nothing left to abort
+                if task.task_type == TaskType::Upload {
+                    task.upload_id = None;
+                }
+                self.discard_progress(task).await;
+                task.status = TaskStatus::Corrupted;
+                task.error_message = Some(e.to_string());
+                Err(e)
+            }
+            Err(e) => {
+                task.status = TaskStatus::Failed;
+                task.error_message = Some(e.to_string());
+                Err(e)
+            }
+        };
+
+        // Final figures stay on the task; only running tasks have a current speed
+        control.refresh_stats(task);
+        task.speed = 0.0;
+        task.eta_seconds = None;
+        task.updated_at = Utc::now();
+        self.save(task).await?;
+        self.emit_progress(task);
+
+        result
+    }
+
+    /// Release server and local resources held by a cancelled or corrupted
+    /// task, so it can only start over from scratch
+    async fn discard_progress(&self, task: &mut TransferTask) {
+        match task.task_type {
+            TaskType::Upload => {
+                if let Some(upload_id) = task.upload_id.take() {
+                    match self.connection().await {
+                        Ok((_, profile_id)) if !belongs_to(task, profile_id.as_deref()) => {
+                            eprintln!("Multipart upload {} is on another profile's server; abort it from there", upload_id);
+                        }
+                        Ok((client, _)) => {
+                            if let Err(e) = client.abort_multipart_upload(&task.bucket_name, &task.object_key, &upload_id).await {
+                                eprintln!("Failed to abort multipart upload {}: {}", upload_id, e);
+                            } else {
+                                println!("Aborted multipart upload {}", upload_id);
+                            }
+                        }
+                        Err(e) => eprintln!("Cannot abort multipart upload {}: {}", upload_id, e),
+                    }
+                }
+                task.completed_parts.clear();
+            }
+            TaskType::Download => {
+                let part_path = part_path(&task.local_path);
+                if let Err(e) = tokio::fs::remove_file(&part_path).await {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        eprintln!("Failed to remove partial download {}: {}", part_path, e);
+                    }
+                }
+                task.completed_parts.clear();
+            }
+        }
+        task.transferred_bytes = 0;
+    }
+
+    /// Pause a task, stopping its in-flight requests. Progress is kept so
+    /// the task can be resumed.
+    pub async fn pause_task(&self, task_id: &str) -> Result<()> {
+        if let Some(control) = self.controls.lock().await.get(task_id) {
+            control.stop(StopReason::Pause);
+            return Ok(());
+        }
+
+        // Not running (e.g. queued); just record the state. Hold the
+        // scheduler lock so the task isn't started underneath us.
+        let _guard = self.dispatch_lock.lock().await;
+        if let Some(control) = self.controls.lock().await.get(task_id) {
+            control.stop(StopReason::Pause);
+            return Ok(());
+        }
+        let db = self.db.lock().await;
+        let mut task = db.get_task(task_id)?
+            .ok_or_else(|| AppError::TaskNotFound(task_id.to_string()))?;
+        if matches!(task.status, TaskStatus::Running | TaskStatus::Pending) {
+            db.update_task_status(task_id, TaskStatus::Paused)?;
+            task.status = TaskStatus::Paused;
+            self.emit_progress(&task);
+        }
+        Ok(())
+    }
+
+    /// Cancel a task, aborting its multipart upload or removing its partial file
+    pub async fn cancel_task(&self, task_id: &str) -> Result<()> {
+        if let Some(control) = self.controls.lock().await.get(task_id) {
+            control.stop(StopReason::Cancel);
+            return Ok(());
+        }
+
+        let _guard = self.dispatch_lock.lock().await;
+        if let Some(control) = self.controls.lock().await.get(task_id) {
+            control.stop(StopReason::Cancel);
+            return Ok(());
+        }
+        self.task_keys.lock().await.remove(task_id);
+
+        let mut task = {
+            let db = self.db.lock().await;
+            db.get_task(task_id)?
+                .ok_or_else(|| AppError::TaskNotFound(task_id.to_string()))?
+        };
+        if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled | TaskStatus::Skipped) {
+            return Ok(());
+        }
+
+        self.discard_progress(&mut task).await;
+        task.status = TaskStatus::Cancelled;
+        task.updated_at = Utc::now();
+        self.save(&task).await?;
+        self.emit_progress(&task);
+        Ok(())
+    }
+
+    /// All tasks, with live progress and statistics for the running ones
+    pub async fn tasks(&self) -> Result<Vec<TransferTask>> {
+        let mut tasks = {
+            let db = self.db.lock().await;
+            db.get_all_tasks()?
+        };
+
+        let controls = self.controls.lock().await;
+        for task in tasks.iter_mut() {
+            if let Some(control) = controls.get(&task.task_id) {
+                control.apply_live(task);
+            }
+        }
+        Ok(tasks)
+    }
+
+    /// Aggregate throughput of the running tasks. The ETA covers only them,
+    /// not tasks still waiting in the queue.
+    pub async fn stats(&self) -> TransferStats {
+        let controls = self.controls.lock().await;
+        let mut stats = TransferStats {
+            running_tasks: controls.len(),
+            ..Default::default()
+        };
+
+        for (task_id, control) in controls.iter() {
+            let task_stats = control.stats.lock().unwrap();
+            let throughput = task_stats.throughput(task_id);
+            match task_stats.task_type {
+                TaskType::Upload => stats.upload_speed += throughput.speed,
+                TaskType::Download => stats.download_speed += throughput.speed,
+            }
+            stats.remaining_bytes += (task_stats.file_size - task_stats.transferred_bytes).max(0);
+            stats.tasks.push(throughput);
+        }
+
+        stats.eta_seconds = stats::eta_seconds(stats.remaining_bytes, stats.upload_speed + stats.download_speed);
+        stats
+    }
+
+    /// Emit `transfer-stats` every second while tasks are running, plus one
+    /// last report once they have all stopped
+    pub async fn report_stats(&self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut was_running = false;
+        loop {
+            interval.tick().await;
+            let stats = self.stats().await;
+            let running = stats.running_tasks > 0;
+            if running || was_running {
+                if let Err(e) = self.app_handle.emit_all("transfer-stats", stats) {
+                    eprintln!("Failed to emit transfer stats: {}", e);
+                }
+            }
+            was_running = running;
+        }
+    }
+
+    /// Cancel every running, paused, pending or interrupted task
+    pub async fn cancel_all(&self) -> Result<usize> {
+        let tasks = {
+            let db = self.db.lock().await;
+            db.get_all_tasks()?
+        };
+
+        let mut cancelled_count = 0;
+        for task in tasks {
+            if matches!(task.status, TaskStatus::Running | TaskStatus::Paused | TaskStatus::Pending | TaskStatus::Interrupted) {
+                self.cancel_task(&task.task_id).await?;
+                cancelled_count += 1;
+            }
+        }
+        Ok(cancelled_count)
+    }
+
+    /// Put a paused, failed, corrupted or interrupted task back in the
+    /// queue. It continues from its persisted progress (none, for a
+    /// corrupted task) once the scheduler starts it.
+    pub async fn resume_task(self: &Arc<Self>, task_id: &str) -> Result<()> {
+        if self.controls.lock().await.contains_key(task_id) {
+            return Err(AppError::Other("Task is already running".to_string()));
+        }
+
+        let mut task = {
+            let db = self.db.lock().await;
+            db.get_task(task_id)?
+                .ok_or_else(|| AppError::TaskNotFound(task_id.to_string()))?
+        };
+
+        // Its upload ID, bucket and key only mean something on its own profile's server
+        let active = self.active_profile.lock().await.clone();
+        check_profile(&task, active.as_deref())?;
+
+        match task.status {
+            TaskStatus::Paused | TaskStatus::Failed | TaskStatus::Corrupted => {
+                let db = self.db.lock().await;
+                db.update_task_status(task_id, TaskStatus::Pending)?;
+                task.status = TaskStatus::Pending;
+            }
+            TaskStatus::Interrupted => {
+                // Its recorded progress can't be trusted until checked against the server
+                let _guard = self.dispatch_lock.lock().await;
+                let (client, profile_id) = self.connection().await?;
+                check_profile(&task, profile_id.as_deref())?;
+                self.reconcile_task(&client, &mut task).await?;
+                task.status = TaskStatus::Pending;
+                task.error_message = None;
+                self.save(&task).await?;
+            }
+            _ => return Err(AppError::Other(format!("Task cannot be resumed from status {:?}", task.status))),
+        }
+        self.emit_progress(&task);
+
+        self.schedule();
+        Ok(())
+    }
+
+    /// Reconcile every interrupted task of the active profile, then queue it
+    /// or leave it paused depending on the `resume_interrupted` setting. Tasks
+    /// that can't be reconciled (e.g. their key is gone) stay interrupted
+    /// with the error; other profiles' tasks wait for their own connection.
+    async fn reconcile_interrupted(&self, client: &MinioClient, profile_id: Option<&str>) {
+        let tasks = {
+            let db = self.db.lock().await;
+            db.get_interrupted_tasks()
+        };
+        let mut tasks: Vec<TransferTask> = match tasks {
+            Ok(tasks) => tasks.into_iter().filter(|t| belongs_to(t, profile_id)).collect(),
+            Err(e) => {
+                eprintln!("[Recovery] Failed to load interrupted tasks: {}", e);
+                return;
+            }
+        };
+        if tasks.is_empty() {
+            return;
+        }
+
+        let resume = self.settings.lock().await.resume_interrupted;
+        println!("[Recovery] Reconciling {} interrupted task(s)", tasks.len());
+        for task in tasks.iter_mut() {
+            match self.reconcile_task(client, task).await {
+                Ok(()) => {
+                    task.status = if resume { TaskStatus::Pending } else { TaskStatus::Paused };
+                    task.error_message = None;
+                }
+                Err(e) => {
+                    eprintln!("[Recovery] Task {} could not be reconciled: {}", task.task_id, e);
+                    task.error_message = Some(e.to_string());
+                }
+            }
+            task.updated_at = Utc::now();
+            if let Err(e) = self.save(task).await {
+                eprintln!("[Recovery] Failed to save task {}: {}", task.task_id, e);
+            }
+            self.emit_progress(task);
+        }
+    }
+
+    /// Bring an interrupted task's recorded progress in line with what
+    /// actually exists. Part progress is only checkpointed periodically, so
+    /// the server may hold parts the database doesn't know about, and the
+    /// upload itself may have been aborted or expired meanwhile.
+    async fn reconcile_task(&self, client: &MinioClient, task: &mut TransferTask) -> Result<()> {
+        match task.task_type {
+            TaskType::Upload => {
+                let Some(upload_id) = task.upload_id.clone() else {
+                    return Ok(());
+                };
+                let sse_c = self.task_key(task).await?;
+                match self.list_uploaded_parts(client, task, &upload_id, sse_c.as_ref()).await? {
+                    Some(server_parts) => {
+                        task.completed_parts = reconcile_parts(task, &server_parts);
+                        println!("[Recovery] Upload {} of task {} has {} of {} parts",
+                            upload_id, task.task_id, task.completed_parts.len(), task.total_parts);
+                    }
+                    None => {
+                        println!("[Recovery] Upload {} of task {} no longer exists; starting over", upload_id, task.task_id);
+                        task.upload_id = None;
+                        task.completed_parts.clear();
+                    }
+                }
+            }
+            TaskType::Download => {
+                // Chunks past the last checkpoint are fetched again; without
+                // the partial file nothing recorded is usable
+                if !Path::new(&part_path(&task.local_path)).exists() {
+                    task.completed_parts.clear();
+                }
+            }
+        }
+        task.transferred_bytes = task.completed_parts.iter().map(|p| p.size).sum();
+        Ok(())
+    }
+
+    /// Parts the server holds for a multipart upload, or `None` if the
+    /// upload no longer exists
+    async fn list_uploaded_parts(
+        &self,
+        client: &MinioClient,
+        task: &TransferTask,
+        upload_id: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<Option<Vec<ModelCompletedPart>>> {
+        let policy = self.retry_policy().await;
+        let mut parts = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let request = client
+                .get_client()
+                .list_parts()
+                .bucket(&task.bucket_name)
+                .key(&task.object_key)
+                .upload_id(upload_id)
+                .set_part_number_marker(marker.clone())
+                .set_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+                .set_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+                .set_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()));
+            let result = policy.run("ListParts", || {}, || async {
+                match request.clone().send().await {
+                    Ok(output) => Ok(Some(output)),
+                    Err(e) if e.as_service_error().and_then(|se| se.code()) == Some("NoSuchUpload") => Ok(None),
+                    Err(e) => Err(retry::classify_sdk_error(e)),
+                }
+            }).await?;
+            let Some(output) = result else {
+                return Ok(None);
+            };
+
+            parts.extend(output.parts().iter().filter_map(|p| Some(ModelCompletedPart {
+                part_number: p.part_number()?,
+                etag: p.e_tag()?.to_string(),
+                size: p.size()?,
+                md5: None,
+            })));
+
+            match output.next_part_number_marker() {
+                Some(next) if output.is_truncated() == Some(true) => marker = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        Ok(Some(parts))
+    }
 
-const PART_SIZE: usize = 5 * 1024 * 1024; // 5MB
-const MAX_CONCURRENT_PARTS: usize = 5;
+    /// Resume an interrupted download from its first missing chunk
+    pub async fn resume_download(self: &Arc<Self>, task_id: &str) -> Result<()> {
+        {
+            let db = self.db.lock().await;
+            let task = db.get_task(task_id)?
+                .ok_or_else(|| AppError::TaskNotFound(task_id.to_string()))?;
+            if task.task_type != TaskType::Download {
+                return Err(AppError::Other("Task is not a download".to_string()));
+            }
+        }
+        self.resume_task(task_id).await
+    }
 
-pub struct TransferManager {
-    db: Arc<Mutex<Database>>,
-    minio_client: Arc<Mutex<Option<MinioClient>>>,
-}
+    /// Pause every queued or running task of a job
+    pub async fn pause_job(&self, job_id: &str) -> Result<()> {
+        let mut tasks = {
+            let db = self.db.lock().await;
+            db.get_job_tasks(job_id)?
+        };
 
-impl TransferManager {
-    pub fn new(db: Arc<Mutex<Database>>, minio_client: Arc<Mutex<Option<MinioClient>>>) -> Self {
-        Self { db, minio_client }
+        // Pause queued tasks first so slots freed by running ones aren't refilled from this job
+        tasks.sort_by_key(|t| t.status != TaskStatus::Pending);
+        for task in tasks {
+            if matches!(task.status, TaskStatus::Running | TaskStatus::Pending) {
+                self.pause_task(&task.task_id).await?;
+            }
+        }
+        Ok(())
     }
 
-    /// Start an upload task
-    pub async fn start_upload(
-        &self,
-        task_id: String,
-        local_path: String,
-        bucket: String,
-        object_key: String,
-        use_encryption: bool,
-        encryption_key: Option<String>,
-    ) -> Result<()> {
-        let file_metadata = tokio::fs::metadata(&local_path).await?;
-        let file_size = file_metadata.len() as i64;
-
-        // Create task record
-        let mut task = TransferTask {
-            task_id: task_id.clone(),
-            task_type: TaskType::Upload,
-            file_name: Path::new(&local_path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-            local_path: local_path.clone(),
-            bucket_name: bucket.clone(),
-            object_key: object_key.clone(),
-            file_size,
-            upload_id: None,
-            part_size: PART_SIZE as i64,
-            total_parts: ((file_size as f64) / (PART_SIZE as f64)).ceil() as i32,
-            completed_parts: vec![],
-            transferred_bytes: 0,
-            status: TaskStatus::Running,
-            error_message: None,
-            use_encryption,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            completed_at: None,
+    /// Requeue every paused, failed, corrupted or interrupted task of a job
+    pub async fn resume_job(self: &Arc<Self>, job_id: &str) -> Result<()> {
+        let tasks = {
+            let db = self.db.lock().await;
+            db.get_job_tasks(job_id)?
         };
 
-        // Save initial task
-        {
-            let db = self.db.lock().await;
-            db.save_task(&task)?;
+        for task in tasks {
+            if matches!(task.status, TaskStatus::Paused | TaskStatus::Failed | TaskStatus::Corrupted | TaskStatus::Interrupted) {
+                self.resume_task(&task.task_id).await?;
+            }
         }
+        Ok(())
+    }
 
-        // Perform upload
-        let result = if file_size < PART_SIZE as i64 {
-            self.upload_small_file(&task, &local_path, &bucket, &object_key, encryption_key.as_deref()).await
-        } else {
-            self.upload_large_file(&mut task, &local_path, &bucket, &object_key, encryption_key.as_deref()).await
+    /// Cancel every unfinished task of a job
+    pub async fn cancel_job(&self, job_id: &str) -> Result<()> {
+        let mut tasks = {
+            let db = self.db.lock().await;
+            db.get_job_tasks(job_id)?
         };
 
-        // Update task status
-        match result {
-            Ok(_) => {
-                task.status = TaskStatus::Completed;
-                task.completed_at = Some(Utc::now());
-                task.transferred_bytes = file_size;
+        tasks.sort_by_key(|t| t.status != TaskStatus::Pending);
+        for task in tasks {
+            if !matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled | TaskStatus::Skipped) {
+                self.cancel_task(&task.task_id).await?;
             }
-            Err(ref e) => {
-                task.status = TaskStatus::Failed;
-                task.error_message = Some(e.to_string());
+        }
+        Ok(())
+    }
+
+    /// Delete a job and its tasks once none of them is running
+    pub async fn delete_job(&self, job_id: &str) -> Result<()> {
+        let db = self.db.lock().await;
+        let tasks = db.get_job_tasks(job_id)?;
+        if tasks.iter().any(|t| t.status == TaskStatus::Running) {
+            return Err(AppError::Other("Pause or cancel the job before deleting it".to_string()));
+        }
+        db.delete_job(job_id)
+    }
+
+    /// Incomplete multipart uploads in `bucket`, linked to the local tasks
+    /// that started them
+    pub async fn multipart_uploads(&self, bucket: &str) -> Result<Vec<MultipartUploadInfo>> {
+        let (client, profile_id) = self.connection().await?;
+        let mut uploads = client.list_multipart_uploads(bucket).await?;
+
+        let db = self.db.lock().await;
+        for upload in uploads.iter_mut() {
+            upload.task_id = db.get_task_by_upload_id(&upload.upload_id)?
+                .filter(|t| belongs_to(t, profile_id.as_deref()))
+                .map(|t| t.task_id);
+        }
+        Ok(uploads)
+    }
+
+    /// Abort one multipart upload and clean up the task that started it.
+    /// Uploads of running tasks are left alone.
+    pub async fn abort_multipart_upload(&self, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        let (client, profile_id) = self.connection().await?;
+        // Keep the scheduler from starting the owning task meanwhile
+        let _guard = self.dispatch_lock.lock().await;
+        self.abort_upload(&client, profile_id.as_deref(), bucket, key, upload_id).await
+    }
+
+    /// Abort every multipart upload in `bucket` started more than
+    /// `older_than_hours` ago, except those of running tasks. Returns how
+    /// many were aborted.
+    pub async fn abort_stale_uploads(&self, bucket: &str, older_than_hours: u32) -> Result<usize> {
+        let (client, profile_id) = self.connection().await?;
+        let cutoff = Utc::now() - chrono::Duration::hours(older_than_hours as i64);
+        let uploads = client.list_multipart_uploads(bucket).await?;
+
+        let _guard = self.dispatch_lock.lock().await;
+        let mut aborted = 0;
+        for upload in uploads {
+            if !is_stale(upload.initiated, cutoff) {
+                continue;
+            }
+            match self.abort_upload(&client, profile_id.as_deref(), bucket, &upload.key, &upload.upload_id).await {
+                Ok(()) => aborted += 1,
+                Err(e) => eprintln!("[Janitor] Skipping upload {} of {}: {}", upload.upload_id, upload.key, e),
             }
         }
+        println!("[Janitor] Aborted {} multipart upload(s) in {} older than {}h", aborted, bucket, older_than_hours);
+        Ok(aborted)
+    }
 
-        task.updated_at = Utc::now();
+    /// Abort an upload on the server, then cancel the local task of the
+    /// active profile that referenced it so it isn't resumed against an
+    /// upload that's gone. Callers hold `dispatch_lock`.
+    async fn abort_upload(&self, client: &MinioClient, profile_id: Option<&str>, bucket: &str, key: &str, upload_id: &str) -> Result<()> {
+        let task = {
+            let db = self.db.lock().await;
+            db.get_task_by_upload_id(upload_id)?
+                .filter(|t| belongs_to(t, profile_id))
+        };
+        if let Some(task) = &task {
+            if self.controls.lock().await.contains_key(&task.task_id) {
+                return Err(AppError::Other(format!(
+                    "Upload {} belongs to running task {}; pause or cancel it first", upload_id, task.task_id
+                )));
+            }
+        }
+
+        client.abort_multipart_upload(bucket, key, upload_id).await?;
+        println!("[Janitor] Aborted multipart upload {} of {}/{}", upload_id, bucket, key);
+
+        if let Some(mut task) = task {
+            task.upload_id = None;
+            task.completed_parts.clear();
+            task.transferred_bytes = 0;
+            task.source_fingerprint = None;
+            if !matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled | TaskStatus::Skipped) {
+                task.status = TaskStatus::Cancelled;
+                task.error_message = Some("Multipart upload was aborted".to_string());
+            }
+            task.updated_at = Utc::now();
+            self.task_keys.lock().await.remove(&task.task_id);
+            self.save(&task).await?;
+            self.emit_progress(&task);
+        }
+        Ok(())
+    }
+
+    /// Set or clear a task's own bandwidth limit (bytes per second). A
+    /// running task picks it up immediately.
+    pub async fn set_bandwidth_limit(&self, task_id: &str, limit: Option<u64>) -> Result<()> {
+        let limit = limit.filter(|l| *l > 0);
+        {
+            let db = self.db.lock().await;
+            db.set_task_bandwidth_limit(task_id, limit.map(|l| l as i64))?;
+        }
+        if let Some(control) = self.controls.lock().await.get(task_id) {
+            control.limiter.set_rate(limit);
+        }
+        Ok(())
+    }
+
+    /// Change a task's priority; takes effect the next time a slot frees
+    pub async fn set_priority(self: &Arc<Self>, task_id: &str, priority: i32) -> Result<()> {
+        {
+            let db = self.db.lock().await;
+            db.set_task_priority(task_id, priority)?;
+        }
+        self.schedule();
+        Ok(())
+    }
+
+    /// Reorder pending tasks; `task_ids` lists them from first to last
+    pub async fn reorder(&self, task_ids: &[String]) -> Result<()> {
         let db = self.db.lock().await;
-        db.save_task(&task)?;
+        db.reorder_tasks(task_ids)
+    }
 
-        result
+    pub async fn settings(&self) -> TransferSettings {
+        self.settings.lock().await.clone()
+    }
+
+    pub async fn update_settings(self: &Arc<Self>, settings: TransferSettings) -> Result<()> {
+        if settings.max_concurrent_tasks == 0 || settings.max_concurrent_parts == 0 || settings.retry_attempts == 0 {
+            return Err(AppError::InvalidConfig("Concurrency limits and retry attempts must be at least 1".to_string()));
+        }
+
+        {
+            let db = self.db.lock().await;
+            db.save_transfer_settings(&settings)?;
+        }
+        // Limits apply to running transfers from their next slice on
+        self.upload_limiter.set_rate(settings.upload_limit);
+        self.download_limiter.set_rate(settings.download_limit);
+        *self.settings.lock().await = settings;
+
+        // Raising the limit may free slots for queued tasks
+        self.schedule();
+        Ok(())
     }
 
-    /// Upload small file (< 5MB) using simple PUT
+    /// Upload a file below the minimum part size with a single PUT carrying
+    /// its Content-MD5, then check the returned ETag
     async fn upload_small_file(
         &self,
+        client: &MinioClient,
         task: &TransferTask,
-        local_path: &str,
-        bucket: &str,
-        object_key: &str,
-        encryption_key: Option<&str>,
+        control: &TaskControl,
+        sse_c: Option<&SseCEncryption>,
     ) -> Result<()> {
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
+        let policy = self.retry_policy().await;
+        let throttle = self.throttle(task, control);
 
-        let body = ByteStream::from_path(Path::new(local_path))
-            .await
-            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        // The file is smaller than one part, so it is hashed and sent from memory
+        let data = bytes::Bytes::from(tokio::fs::read(&task.local_path).await?);
+        let digest = integrity::md5(&data);
 
-        let mut put_request = client
-            .get_client()
-            .put_object()
-            .bucket(bucket)
-            .key(object_key)
-            .body(body);
-        
-        // Add SSE-C headers if encryption is enabled
-        if let Some(key_base64) = encryption_key {
-            use crate::encryption::SseCEncryption;
-            let key_bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, key_base64)
-                .map_err(|e| AppError::Encryption(e.to_string()))?;
-            let sse_c = SseCEncryption::new(key_bytes)?;
-            
-            put_request = put_request
-                .sse_customer_algorithm(sse_c.get_algorithm())
-                .sse_customer_key(sse_c.get_key_base64())
-                .sse_customer_key_md5(sse_c.get_key_md5());
-        }
-        
-        put_request
-            .send()
-            .await
-            .map_err(|e| AppError::S3(e.to_string()))?;
+        let output = control.run(policy.run("PUT object", || control.record_retry(), || async {
+            let body = if throttle.is_limited() {
+                throttled_stream(data.clone(), &throttle)
+            } else {
+                ByteStream::from(data.clone())
+            };
+
+            let mut put_request = client
+                .get_client()
+                .put_object()
+                .bucket(&task.bucket_name)
+                .key(&task.object_key)
+                .content_md5(integrity::content_md5(&digest))
+                .body(body);
+
+            // Add SSE-C headers if encryption is enabled
+            if let Some(sse_c) = sse_c {
+                put_request = put_request
+                    .sse_customer_algorithm(sse_c.get_algorithm())
+                    .sse_customer_key(sse_c.get_key_base64())
+                    .sse_customer_key_md5(sse_c.get_key_md5());
+            }
+
+            put_request
+                .send()
+                .await
+                .map_err(retry::classify_sdk_error)
+        })).await?;
+
+        // Encrypted objects' ETags aren't the MD5 of their content
+        if sse_c.is_none() && output.server_side_encryption().is_none() {
+            check_upload_etag(output.e_tag(), &integrity::to_hex(&digest))?;
+        }
 
         Ok(())
     }
 
-    /// Upload large file using multipart upload
-    async fn upload_large_file(
+    /// Initiate a multipart upload and persist its upload id
+    async fn create_multipart_upload(
         &self,
+        client: &MinioClient,
         task: &mut TransferTask,
-        local_path: &str,
-        bucket: &str,
-        object_key: &str,
-        encryption_key: Option<&str>,
+        sse_c: Option<&SseCEncryption>,
     ) -> Result<()> {
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
-
-        // Prepare SSE-C encryption if enabled
-        let sse_c = if let Some(key_base64) = encryption_key {
-            use crate::encryption::SseCEncryption;
-            let key_bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, key_base64)
-                .map_err(|e| AppError::Encryption(e.to_string()))?;
-            Some(SseCEncryption::new(key_bytes)?)
-        } else {
-            None
-        };
+        let bucket = task.bucket_name.as_str();
+        let object_key = task.object_key.as_str();
 
-        // Initiate multipart upload
         let mut multipart_request = client
             .get_client()
             .create_multipart_upload()
             .bucket(bucket)
             .key(object_key);
-        
+
         // Try to set content type based on file extension
         let content_type = if object_key.to_lowercase().ends_with(".mp4") {
             Some("video/mp4")
@@ -179,556 +1406,1078 @@ impl TransferManager {
         } else {
             None
         };
-        
+
         if let Some(ct) = content_type {
             println!("[upload_large_file] Setting content-type: {}", ct);
             multipart_request = multipart_request.content_type(ct);
         }
-        
+
         // Add SSE-C headers if encryption is enabled
-        if let Some(ref encryption) = sse_c {
+        if let Some(encryption) = sse_c {
             println!("[upload_large_file] Adding SSE-C headers to multipart upload");
             multipart_request = multipart_request
                 .sse_customer_algorithm(encryption.get_algorithm())
                 .sse_customer_key(encryption.get_key_base64())
                 .sse_customer_key_md5(encryption.get_key_md5());
         }
-        
+
         println!("[upload_large_file] Initiating multipart upload for {}/{}", bucket, object_key);
-        let multipart_upload = multipart_request
-            .send()
-            .await
-            .map_err(|e| {
-                eprintln!("[upload_large_file] Failed to initiate multipart upload: {:?}", e);
-                AppError::S3(format!("Failed to initiate multipart upload: {}", e))
-            })?;
+        let policy = self.retry_policy().await;
+        let multipart_upload = policy.run("CreateMultipartUpload", || {}, || async {
+            multipart_request
+                .clone()
+                .send()
+                .await
+                .map_err(|e| {
+                    eprintln!("[upload_large_file] Failed to initiate multipart upload: {:?}", e);
+                    retry::classify_sdk_error(e)
+                })
+        }).await?;
 
         let upload_id = multipart_upload
             .upload_id()
             .ok_or(AppError::S3("No upload ID".to_string()))?
             .to_string();
 
-        task.upload_id = Some(upload_id.clone());
-        
+        task.upload_id = Some(upload_id);
+        task.completed_parts.clear();
+        task.transferred_bytes = 0;
+
         // Save task with upload_id
-        {
-            let db = self.db.lock().await;
-            db.save_task(task)?;
-        }
+        self.save(task).await
+    }
+
+    /// Upload every part not yet in `task.completed_parts`, then complete
+    /// the multipart upload. Used both for new uploads and for resume.
+    ///
+    /// Up to `max_concurrent_parts` parts are in flight at once. A part is
+    /// only read from disk once a slot is free, so memory stays around
+    /// part_size × concurrency.
+    async fn upload_parts(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        control: &TaskControl,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        let upload_id = task.upload_id.clone()
+            .ok_or(AppError::Other("No upload ID".to_string()))?;
+        let part_size = task.part_size as usize;
+        let concurrency = self.settings.lock().await.max_concurrent_parts.max(1);
+        let policy = self.retry_policy().await;
+        let throttle = self.throttle(task, control);
 
-        // Upload parts
-        let mut file = File::open(local_path).await?;
-        let mut part_number = 1;
-        let mut completed_parts = Vec::new();
-        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_PARTS));
+        let mut file = File::open(&task.local_path).await?;
         let file_size = file.metadata().await?.len();
 
-        println!("[upload_large_file] File size: {} bytes, will upload {} parts", file_size, task.total_parts);
+        println!("[upload_large_file] File size: {} bytes, {} parts, {} already uploaded, {} parallel",
+            file_size, task.total_parts, task.completed_parts.len(), concurrency);
 
-        loop {
-            let mut buffer = vec![0u8; PART_SIZE];
+        let mut in_flight = JoinSet::new();
+        let pending_parts: Vec<i32> = (1..=task.total_parts)
+            .filter(|n| !task.completed_parts.iter().any(|p| p.part_number == *n))
+            .collect();
+
+        for part_number in pending_parts {
+            // Wait for a free slot before reading the next part into memory
+            while in_flight.len() >= concurrency {
+                self.record_part(task, control, &mut in_flight).await?;
+            }
+            control.check()?;
+
+            let offset = (part_number as u64 - 1) * part_size as u64;
+            file.seek(std::io::SeekFrom::Start(offset)).await?;
+
+            let mut buffer = vec![0u8; part_size];
             let mut total_read = 0;
-            
+
             // Keep reading until we fill the buffer or reach EOF
-            while total_read < PART_SIZE {
+            while total_read < part_size {
                 let bytes_read = file.read(&mut buffer[total_read..]).await?;
-                
                 if bytes_read == 0 {
-                    // EOF reached
                     break;
                 }
-                
                 total_read += bytes_read;
             }
-            
+
             if total_read == 0 {
-                // No more data to read
                 break;
             }
 
             buffer.truncate(total_read);
-            
+            let digest = integrity::md5(&buffer);
+            let body = bytes::Bytes::from(buffer);
+
             println!("[upload_large_file] Uploading part {}/{} ({} bytes)", part_number, task.total_parts, total_read);
-            
-            let permit = semaphore.clone().acquire_owned().await.unwrap();
-            let client_clone = client.get_client().clone();
-            let bucket_clone = bucket.to_string();
-            let key_clone = object_key.to_string();
-            let upload_id_clone = upload_id.clone();
-            let part_num = part_number;
-            let encryption_clone = sse_c.clone();
-            let bytes_count = total_read;
-
-            let upload_result = tokio::spawn(async move {
-                let _permit = permit;
-                
-                let mut upload_part_request = client_clone
-                    .upload_part()
-                    .bucket(&bucket_clone)
-                    .key(&key_clone)
-                    .upload_id(&upload_id_clone)
-                    .part_number(part_num)
-                    .body(ByteStream::from(buffer));
-                
-                // Add SSE-C headers if encryption is enabled
-                if let Some(ref encryption) = encryption_clone {
-                    upload_part_request = upload_part_request
-                        .sse_customer_algorithm(encryption.get_algorithm())
-                        .sse_customer_key(encryption.get_key_base64())
-                        .sse_customer_key_md5(encryption.get_key_md5());
-                }
-                
-                let result = upload_part_request.send().await;
-                
-                if let Err(ref e) = result {
-                    eprintln!("[upload_large_file] Part {} upload failed: {:?}", part_num, e);
+
+            let s3 = client.get_client().clone();
+            let bucket = task.bucket_name.clone();
+            let object_key = task.object_key.clone();
+            let upload_id = upload_id.clone();
+            let sse_c = sse_c.cloned();
+            let policy = policy.clone();
+            let throttle = throttle.clone();
+            let control = control.clone();
+            in_flight.spawn(async move {
+                let what = format!("Part {}", part_number);
+                let output = control.run(policy.run(&what, || control.record_retry(), || async {
+                    let mut upload_part_request = s3
+                        .upload_part()
+                        .bucket(&bucket)
+                        .key(&object_key)
+                        .upload_id(&upload_id)
+                        .part_number(part_number)
+                        .content_md5(integrity::content_md5(&digest))
+                        .body(if throttle.is_limited() {
+                            throttled_stream(body.clone(), &throttle)
+                        } else {
+                            ByteStream::from(body.clone())
+                        });
+
+                    // Add SSE-C headers if encryption is enabled
+                    if let Some(encryption) = &sse_c {
+                        upload_part_request = upload_part_request
+                            .sse_customer_algorithm(encryption.get_algorithm())
+                            .sse_customer_key(encryption.get_key_base64())
+                            .sse_customer_key_md5(encryption.get_key_md5());
+                    }
+
+                    upload_part_request.send().await.map_err(|e| {
+                        eprintln!("[upload_large_file] Part {} upload failed: {:?}", part_number, e);
+                        retry::classify_sdk_error(e)
+                    })
+                })).await?;
+
+                Ok(ModelCompletedPart {
+                    part_number,
+                    etag: output.e_tag().unwrap_or("").to_string(),
+                    size: total_read as i64,
+                    md5: Some(integrity::to_hex(&digest)),
+                })
+            });
+        }
+
+        while !in_flight.is_empty() {
+            self.record_part(task, control, &mut in_flight).await?;
+        }
+
+        control.check()?;
+
+        // Sort completed parts by part number before completing upload
+        let mut parts = task.completed_parts.clone();
+        parts.sort_by_key(|p| p.part_number);
+        let completed_parts = parts.iter()
+            .map(|p| CompletedPart::builder()
+                .part_number(p.part_number)
+                .e_tag(&p.etag)
+                .build())
+            .collect::<Vec<_>>();
+
+        let completed_upload = CompletedMultipartUpload::builder()
+            .set_parts(Some(completed_parts))
+            .build();
+
+        println!("[upload_large_file] Completing multipart upload with {} parts", parts.len());
+        let complete_request = client
+            .get_client()
+            .complete_multipart_upload()
+            .bucket(&task.bucket_name)
+            .key(&task.object_key)
+            .upload_id(&upload_id)
+            .multipart_upload(completed_upload);
+        let output = policy.run("CompleteMultipartUpload", || control.record_retry(), || async {
+            complete_request
+                .clone()
+                .send()
+                .await
+                .map_err(|e| {
+                    eprintln!("[upload_large_file] Failed to complete multipart upload: {:?}", e);
+                    retry::classify_sdk_error(e)
+                })
+        }).await?;
+
+        // Parts recorded before checksums were kept can't be verified
+        let digests: Option<Vec<_>> = parts.iter()
+            .map(|p| p.md5.as_deref().and_then(integrity::from_hex))
+            .collect();
+        match digests {
+            Some(digests) if sse_c.is_none() && output.server_side_encryption().is_none() => {
+                check_upload_etag(output.e_tag(), &integrity::composite_etag(&digests))?;
+            }
+            _ => println!("[upload_large_file] Skipping ETag verification for {}", task.object_key),
+        }
+
+        println!("[upload_large_file] Multipart upload completed successfully");
+        Ok(())
+    }
+
+    /// Wait for the next part upload (or download chunk) to finish and
+    /// persist it. On error the remaining requests are aborted when the
+    /// JoinSet is dropped.
+    async fn record_part(
+        &self,
+        task: &mut TransferTask,
+        control: &TaskControl,
+        in_flight: &mut JoinSet<Result<ModelCompletedPart>>,
+    ) -> Result<()> {
+        let Some(joined) = in_flight.join_next().await else {
+            return Ok(());
+        };
+        let part = joined.map_err(|e| AppError::Other(format!("Part transfer task failed: {}", e)))??;
+
+        println!("Part {} of task {} finished ({} bytes)", part.part_number, task.task_id, part.size);
+
+        // Parts finish in any order; the UI hears about each one, the
+        // database only at checkpoints
+        let size = part.size;
+        task.transferred_bytes += size;
+        task.completed_parts.push(part);
+        control.record_progress(task, size);
+        control.flush_retries(task);
+        task.updated_at = Utc::now();
+        self.checkpoint(task, control).await
+    }
+
+    async fn run_download(&self, client: &MinioClient, task: &mut TransferTask, control: &TaskControl, sse_c: Option<&SseCEncryption>) -> Result<()> {
+        // Folder downloads recreate the key hierarchy locally
+        if let Some(parent) = Path::new(&task.local_path).parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // The target is checked before the first byte; a keep-both download
+        // may move to a new name here
+        if task.completed_parts.is_empty() {
+            self.settle_local_target(client, task, sse_c).await?;
+        }
+        let part_path = part_path(&task.local_path);
+
+        // Chunks already on disk are only reusable if the object is unchanged
+        if !task.completed_parts.is_empty() {
+            self.check_remote_unchanged(client, task, &part_path, sse_c).await?;
+        }
+
+        if task.file_size < MIN_PART_SIZE {
+            println!("Starting small file download for task {}: {} bytes", task.task_id, task.file_size);
+            let bytes_downloaded = self.download_small_file(client, task, control, &part_path, sse_c).await?;
+            println!("Small file download completed for task {}: {} bytes", task.task_id, bytes_downloaded);
+        } else {
+            println!("Starting large file download for task {}: {} bytes", task.task_id, task.file_size);
+            self.download_large_file(client, task, control, &part_path, sse_c).await?;
+        }
+
+        self.verify_download(client, task, &part_path, sse_c).await?;
+
+        // Another transfer may have created the target in the meantime
+        if Path::new(&task.local_path).exists() {
+            match task.conflict_policy {
+                ConflictPolicy::Skip => {
+                    let _ = tokio::fs::remove_file(&part_path).await;
+                    return Err(AppError::Skipped(format!("{} already exists", task.local_path)));
                 }
-                
-                result.map(|output| (part_num, output.e_tag().unwrap_or("").to_string()))
-            })
-            .await
-            .map_err(|e| {
-                eprintln!("[upload_large_file] Tokio join error: {:?}", e);
-                AppError::Other(e.to_string())
-            })?
-            .map_err(|e| {
-                eprintln!("[upload_large_file] S3 upload part error: {:?}", e);
-                AppError::S3(format!("Part upload failed: {}", e))
-            })?;
+                ConflictPolicy::KeepBoth => self.move_to_free_path(task).await?,
+                ConflictPolicy::Overwrite | ConflictPolicy::OverwriteIfNewer => {}
+            }
+        }
 
-            completed_parts.push(CompletedPart::builder()
-                .part_number(upload_result.0)
-                .e_tag(upload_result.1.clone())
-                .build());
+        // Only a complete, verified download appears under the target name
+        tokio::fs::rename(&part_path, &task.local_path).await?;
+        sync_parent_dir(&task.local_path).await;
+        Ok(())
+    }
 
-            println!("[upload_large_file] Part {} uploaded successfully, etag: {}", upload_result.0, upload_result.1);
+    /// Apply the conflict policy to an existing local target before a
+    /// download starts
+    async fn settle_local_target(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        if !Path::new(&task.local_path).exists() {
+            return Ok(());
+        }
+        match task.conflict_policy {
+            ConflictPolicy::Overwrite => Ok(()),
+            ConflictPolicy::Skip => Err(AppError::Skipped(format!("{} already exists", task.local_path))),
+            ConflictPolicy::KeepBoth => self.move_to_free_path(task).await,
+            ConflictPolicy::OverwriteIfNewer => {
+                let remote = self.head_target(client, &task.bucket_name, &task.object_key, task.version_id.as_deref(), sse_c).await?;
+                let local = file_modified(&task.local_path).await?;
+                if conflict::source_is_newer(remote.as_ref().and_then(object_modified), local) {
+                    Ok(())
+                } else {
+                    Err(AppError::Skipped(format!("{} is up to date", task.local_path)))
+                }
+            }
+        }
+    }
 
-            // Update task progress
-            task.completed_parts.push(ModelCompletedPart {
-                part_number: upload_result.0,
-                etag: upload_result.1,
-                size: bytes_count as i64,
-            });
-            task.transferred_bytes += bytes_count as i64;
+    /// Point a download at the first numbered name that neither exists nor
+    /// is being downloaded to
+    async fn move_to_free_path(&self, task: &mut TransferTask) -> Result<()> {
+        let target = PathBuf::from(&task.local_path);
+        for n in 1..=conflict::MAX_NUMBERED_NAMES {
+            let candidate = conflict::numbered_path(&target, n);
+            let candidate = candidate.to_string_lossy().into_owned();
+            if Path::new(&candidate).exists() || Path::new(&part_path(&candidate)).exists() {
+                continue;
+            }
+            println!("Task {}: {} exists, downloading to {}", task.task_id, task.local_path, candidate);
+            task.file_name = Path::new(&candidate)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+            task.local_path = candidate;
             task.updated_at = Utc::now();
+            return self.save(task).await;
+        }
+        Err(AppError::Other(format!("No free file name for {}", task.local_path)))
+    }
 
-            {
-                let db = self.db.lock().await;
-                db.save_task(task)?;
+    /// Fingerprint the source before an upload starts, and make sure a
+    /// resumed upload still reads the same file. If the file changed, the
+    /// parts already sent are discarded and the upload starts over rather
+    /// than mixing old and new content in one object.
+    async fn check_source_unchanged(&self, task: &mut TransferTask, control: &TaskControl, fresh: bool) -> Result<()> {
+        let path = PathBuf::from(&task.local_path);
+        let current = tokio::task::spawn_blocking(move || integrity::fingerprint(&path))
+            .await
+            .map_err(|e| AppError::Other(e.to_string()))??;
+
+        if !fresh {
+            if task.source_fingerprint.as_ref() == Some(&current) {
+                return Ok(());
+            }
+            println!(
+                "Restarting upload for task {}: source changed since the upload began ({:?} -> {:?})",
+                task.task_id, task.source_fingerprint, current
+            );
+            self.discard_progress(task).await;
+        }
+
+        // The file may also have changed between queueing and starting
+        if current.size != task.file_size {
+            task.file_size = current.size;
+            task.part_size = upload_part_size(current.size, Some(task.part_size))
+                .or_else(|_| upload_part_size(current.size, None))?;
+            task.total_parts = part_count(current.size, task.part_size);
+        }
+        task.source_fingerprint = Some(current);
+        task.updated_at = Utc::now();
+        control.record_progress(task, 0);
+        self.save(task).await?;
+        self.emit_progress(task);
+        Ok(())
+    }
+
+    /// Apply the conflict policy to an existing object before an upload
+    /// sends anything. Overwriting needs no request at all.
+    async fn settle_remote_target(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        if task.conflict_policy == ConflictPolicy::Overwrite {
+            return Ok(());
+        }
+        let Some(existing) = self.head_target(client, &task.bucket_name, &task.object_key, None, sse_c).await? else {
+            return Ok(());
+        };
+        match task.conflict_policy {
+            ConflictPolicy::Overwrite => Ok(()),
+            ConflictPolicy::Skip => Err(AppError::Skipped(format!(
+                "{}/{} already exists", task.bucket_name, task.object_key
+            ))),
+            ConflictPolicy::KeepBoth => {
+                for n in 1..=conflict::MAX_NUMBERED_NAMES {
+                    let candidate = conflict::numbered_key(&task.object_key, n);
+                    if self.head_target(client, &task.bucket_name, &candidate, None, sse_c).await?.is_some() {
+                        continue;
+                    }
+                    println!("Task {}: {} exists, uploading as {}", task.task_id, task.object_key, candidate);
+                    task.object_key = candidate;
+                    task.updated_at = Utc::now();
+                    return self.save(task).await;
+                }
+                Err(AppError::Other(format!("No free object key for {}", task.object_key)))
+            }
+            ConflictPolicy::OverwriteIfNewer => {
+                let local = file_modified(&task.local_path).await?;
+                if conflict::source_is_newer(local, object_modified(&existing)) {
+                    Ok(())
+                } else {
+                    Err(AppError::Skipped(format!(
+                        "{}/{} is up to date", task.bucket_name, task.object_key
+                    )))
+                }
             }
-
-            part_number += 1;
         }
+    }
 
-        // Sort completed parts by part number before completing upload
-        completed_parts.sort_by_key(|p| p.part_number);
-        
-        // Complete multipart upload
-        let completed_upload = CompletedMultipartUpload::builder()
-            .set_parts(Some(completed_parts))
-            .build();
-
-        println!("[upload_large_file] Completing multipart upload with {} parts", task.completed_parts.len());
-        println!("[upload_large_file] Parts: {:?}", task.completed_parts.iter().map(|p| format!("{}:{}", p.part_number, p.size)).collect::<Vec<_>>());
-        client
+    /// HEAD an object (the latest version unless `version_id` is given),
+    /// or `None` if it doesn't exist
+    async fn head_target(
+        &self,
+        client: &MinioClient,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<Option<HeadObjectOutput>> {
+        let request = client
             .get_client()
-            .complete_multipart_upload()
+            .head_object()
             .bucket(bucket)
-            .key(object_key)
-            .upload_id(&upload_id)
-            .multipart_upload(completed_upload)
-            .send()
+            .key(key)
+            .set_version_id(version_id.map(|v| v.to_string()))
+            .set_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+            .set_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+            .set_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()));
+        self.retry_policy().await
+            .run("HEAD object", || {}, || async {
+                match request.clone().send().await {
+                    Ok(output) => Ok(Some(output)),
+                    Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(None),
+                    Err(e) => Err(retry::classify_sdk_error(e)),
+                }
+            })
             .await
-            .map_err(|e| {
-                eprintln!("[upload_large_file] Failed to complete multipart upload: {:?}", e);
-                AppError::S3(format!("Failed to complete multipart upload: {}", e))
-            })?;
-
-        println!("[upload_large_file] Multipart upload completed successfully");
-        Ok(())
     }
 
-    /// Resume an upload task
-    pub async fn resume_upload(&self, task_id: &str) -> Result<()> {
-        let mut task = {
-            let db = self.db.lock().await;
-            db.get_task(task_id)?
-                .ok_or(AppError::TaskNotFound(task_id.to_string()))?
+    /// Check the finished `.part` file against the object's ETag. Objects
+    /// whose ETag isn't derived from their content (encrypted, or written
+    /// with uneven part sizes) are accepted unchecked.
+    async fn verify_download(
+        &self,
+        client: &MinioClient,
+        task: &TransferTask,
+        part_path: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        let Some(etag) = task.remote_etag.clone() else {
+            return Ok(());
         };
-
-        if task.task_type != TaskType::Upload {
-            return Err(AppError::Other("Not an upload task".to_string()));
+        if sse_c.is_some() {
+            println!("Skipping verification of task {}: object is encrypted", task.task_id);
+            return Ok(());
         }
-
-        if task.upload_id.is_none() {
-            return Err(AppError::Other("No upload ID found".to_string()));
+        let kind = integrity::parse_etag(&etag);
+        if kind == EtagKind::Opaque {
+            println!("Skipping verification of task {}: ETag {} is not a content hash", task.task_id, etag);
+            return Ok(());
         }
 
-        task.status = TaskStatus::Running;
-        task.updated_at = Utc::now();
-
-        {
-            let db = self.db.lock().await;
-            db.save_task(&task)?;
+        // For multipart objects, part 1's size gives the layout to hash with
+        let mut head_request = client
+            .get_client()
+            .head_object()
+            .bucket(&task.bucket_name)
+            .key(&task.object_key)
+            .set_version_id(task.version_id.clone());
+        if let EtagKind::Multipart(..) = kind {
+            head_request = head_request.part_number(1);
         }
+        let head = self.retry_policy().await
+            .run("HEAD object", || {}, || async {
+                head_request.clone().send().await.map_err(retry::classify_sdk_error)
+            })
+            .await?;
 
-        // Continue uploading remaining parts
-        self.continue_multipart_upload(&mut task).await
-    }
-
-    async fn continue_multipart_upload(&self, task: &mut TransferTask) -> Result<()> {
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
-
-        let upload_id = task.upload_id.as_ref()
-            .ok_or(AppError::Other("No upload ID".to_string()))?;
+        if head.server_side_encryption().is_some() || head.sse_customer_algorithm().is_some() {
+            println!("Skipping verification of task {}: object is encrypted", task.task_id);
+            return Ok(());
+        }
 
-        let mut file = File::open(&task.local_path).await?;
-        let completed_part_numbers: Vec<i32> = task.completed_parts
-            .iter()
-            .map(|p| p.part_number)
-            .collect();
+        let part_size = match kind {
+            EtagKind::Multipart(_, parts) => {
+                let first_part = head.content_length().unwrap_or(0);
+                if first_part <= 0 || div_ceil(task.file_size, first_part) != parts as i64 {
+                    println!("Skipping verification of task {}: parts of {} are not evenly sized", task.task_id, etag);
+                    return Ok(());
+                }
+                Some(first_part as u64)
+            }
+            _ => None,
+        };
 
-        let mut part_number = 1;
-        let mut completed_parts_for_s3 = Vec::new();
+        let path = PathBuf::from(part_path);
+        let computed = tokio::task::spawn_blocking(move || integrity::file_etag(&path, part_size))
+            .await
+            .map_err(|e| AppError::Other(e.to_string()))??;
 
-        // Convert existing completed parts
-        for cp in &task.completed_parts {
-            completed_parts_for_s3.push(CompletedPart::builder()
-                .part_number(cp.part_number)
-                .e_tag(&cp.etag)
-                .build());
+        if !integrity::etags_match(&etag, &computed) {
+            return Err(AppError::Integrity(format!(
+                "downloaded data hashes to {}, object ETag is {}", computed, etag
+            )));
         }
+        println!("Verified download for task {} against ETag {}", task.task_id, etag);
+        Ok(())
+    }
 
-        loop {
-            let mut buffer = vec![0u8; PART_SIZE];
-            let offset = ((part_number - 1) as usize) * PART_SIZE;
-            file.seek(std::io::SeekFrom::Start(offset as u64)).await?;
-            
-            let bytes_read = file.read(&mut buffer).await?;
-            if bytes_read == 0 {
-                break;
-            }
+    /// Compare the object with the ETag and size recorded when the task
+    /// began. If it changed, or the partial file is gone, start over.
+    async fn check_remote_unchanged(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        part_path: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        let head_result = self.head_target(client, &task.bucket_name, &task.object_key, task.version_id.as_deref(), sse_c).await?
+            .ok_or_else(|| AppError::S3(format!("{}/{} no longer exists", task.bucket_name, task.object_key)))?;
+
+        let remote_etag = head_result.e_tag().map(|e| e.to_string());
+        let remote_size = head_result.content_length().unwrap_or(0);
+        let etag_changed = task.remote_etag.is_some() && task.remote_etag != remote_etag;
+        let part_missing = !Path::new(part_path).exists();
+
+        if etag_changed || remote_size != task.file_size || part_missing {
+            println!(
+                "Restarting download for task {}: remote etag {:?} -> {:?}, size {} -> {}, partial file present: {}",
+                task.task_id, task.remote_etag, remote_etag, task.file_size, remote_size, !part_missing
+            );
+            let _ = tokio::fs::remove_file(part_path).await;
+            task.remote_etag = remote_etag;
+            task.file_size = remote_size;
+            task.part_size = download_chunk_size(remote_size);
+            task.total_parts = part_count(remote_size, task.part_size);
+            task.completed_parts.clear();
+            task.transferred_bytes = 0;
+            task.updated_at = Utc::now();
+            self.save(task).await?;
+        }
 
-            // Skip already uploaded parts
-            if completed_part_numbers.contains(&part_number) {
-                part_number += 1;
-                continue;
-            }
+        Ok(())
+    }
 
-            buffer.truncate(bytes_read);
+    async fn download_small_file(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        control: &TaskControl,
+        part_path: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<i64> {
+        println!("Starting download for task {}: {} bytes", task.task_id, task.file_size);
 
-            let upload_result = client
+        let policy = self.retry_policy().await;
+        let throttle = self.throttle(task, control);
+        let bytes = control.run(policy.run("GET object", || control.record_retry(), || async {
+            let mut result = client
                 .get_client()
-                .upload_part()
+                .get_object()
                 .bucket(&task.bucket_name)
                 .key(&task.object_key)
-                .upload_id(upload_id)
-                .part_number(part_number)
-                .body(ByteStream::from(buffer))
+                .set_version_id(task.version_id.clone())
+                .set_if_match(task.remote_etag.clone())
+                .set_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+                .set_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+                .set_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()))
                 .send()
                 .await
-                .map_err(|e| AppError::S3(e.to_string()))?;
+                .map_err(retry::classify_sdk_error)?;
+
+            // A body cut off mid-stream is worth another attempt
+            let mut data = Vec::with_capacity(task.file_size.max(0) as usize);
+            while let Some(chunk) = result.body.next().await {
+                let chunk = chunk.map_err(|e| AppError::Transient(e.to_string()))?;
+                throttle.acquire(chunk.len()).await;
+                data.extend_from_slice(&chunk);
+            }
+            Ok(data)
+        })).await?;
 
-            let etag = upload_result.e_tag().unwrap_or("").to_string();
+        let mut file = File::create(part_path).await?;
+        file.write_all(&bytes).await?;
+        file.sync_all().await?;
 
-            completed_parts_for_s3.push(CompletedPart::builder()
-                .part_number(part_number)
-                .e_tag(&etag)
-                .build());
+        task.transferred_bytes = bytes.len() as i64;
+        control.record_progress(task, task.transferred_bytes);
+        task.updated_at = Utc::now();
+        self.emit_progress(task);
 
-            task.completed_parts.push(ModelCompletedPart {
-                part_number,
-                etag,
-                size: bytes_read as i64,
-            });
-            task.transferred_bytes += bytes_read as i64;
-            task.updated_at = Utc::now();
+        println!("Download completed for task {}: {} bytes", task.task_id, bytes.len());
 
-            {
-                let db = self.db.lock().await;
-                db.save_task(task)?;
-            }
+        Ok(bytes.len() as i64)
+    }
 
-            part_number += 1;
+    /// Download every chunk not yet recorded in `task.completed_parts` into
+    /// the preallocated `.part` file. Up to `max_concurrent_parts` ranges are
+    /// fetched at once over the connection's shared HTTP client and written
+    /// at their offsets. A chunk is recorded only after its full range has
+    /// been written and synced, so a restart can continue from the first
+    /// missing chunk.
+    async fn download_large_file(
+        &self,
+        client: &MinioClient,
+        task: &mut TransferTask,
+        control: &TaskControl,
+        part_path: &str,
+        sse_c: Option<&SseCEncryption>,
+    ) -> Result<()> {
+        // Never truncate: a resumed download keeps the chunks already written
+        let file = OpenOptions::new().create(true).write(true).truncate(false).open(part_path).await?;
+        file.set_len(task.file_size as u64).await?;
+        let file = Arc::new(file.into_std().await);
+
+        let etag = task.remote_etag.clone().unwrap_or_default();
+        task.completed_parts.retain(|p| p.etag == etag);
+        task.transferred_bytes = task.completed_parts.iter().map(|p| p.size).sum();
+        if task.transferred_bytes > 0 {
+            println!("Resuming download for task {}: {} of {} chunks already on disk",
+                task.task_id, task.completed_parts.len(), task.total_parts);
         }
 
-        // Complete multipart upload
-        let completed_upload = CompletedMultipartUpload::builder()
-            .set_parts(Some(completed_parts_for_s3))
-            .build();
+        // 使用预签名URL进行下载；每个分块只需在请求头中指定 Range
+        let presigned_config = aws_sdk_s3::presigning::PresigningConfig::builder()
+            .expires_in(std::time::Duration::from_secs(3600)) // 1小时过期
+            .build()
+            .map_err(|e| AppError::S3(e.to_string()))?;
 
-        client
+        let presigned_request = client
             .get_client()
-            .complete_multipart_upload()
+            .get_object()
             .bucket(&task.bucket_name)
             .key(&task.object_key)
-            .upload_id(upload_id)
-            .multipart_upload(completed_upload)
-            .send()
+            .set_version_id(task.version_id.clone())
+            .set_sse_customer_algorithm(sse_c.map(|k| k.get_algorithm().to_string()))
+            .set_sse_customer_key(sse_c.map(|k| k.get_key_base64()))
+            .set_sse_customer_key_md5(sse_c.map(|k| k.get_key_md5().to_string()))
+            .presigned(presigned_config)
             .await
-            .map_err(|e| AppError::S3(e.to_string()))?;
+            .map_err(|e| {
+                println!("Failed to create presigned URL for task {}: {}", task.task_id, e);
+                AppError::S3(e.to_string())
+            })?;
+        let url = presigned_request.uri().to_string();
+        // SSE-C parameters are signed as headers, not query parameters, so
+        // each ranged request has to send them too
+        let mut signed_headers = reqwest::header::HeaderMap::new();
+        for (name, value) in presigned_request.headers() {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| AppError::Other(e.to_string()))?;
+            let value = reqwest::header::HeaderValue::from_str(value)
+                .map_err(|e| AppError::Other(e.to_string()))?;
+            signed_headers.insert(name, value);
+        }
 
-        task.status = TaskStatus::Completed;
-        task.completed_at = Some(Utc::now());
-        task.updated_at = Utc::now();
+        let concurrency = self.settings.lock().await.max_concurrent_parts.max(1);
+        let policy = self.retry_policy().await;
+        let throttle = self.throttle(task, control);
+        println!("Downloading {} chunks of {} bytes for task {}, {} parallel",
+            task.total_parts, task.part_size, task.task_id, concurrency);
 
-        let db = self.db.lock().await;
-        db.save_task(task)?;
+        let mut in_flight = JoinSet::new();
+        let pending_chunks: Vec<i32> = (1..=task.total_parts)
+            .filter(|n| !task.completed_parts.iter().any(|p| p.part_number == *n))
+            .collect();
 
-        Ok(())
-    }
+        for chunk in pending_chunks {
+            while in_flight.len() >= concurrency {
+                self.record_part(task, control, &mut in_flight).await?;
+            }
+            control.check()?;
+
+            let offset = (chunk as i64 - 1) * task.part_size;
+            let end = std::cmp::min(offset + task.part_size - 1, task.file_size - 1);
+
+            // 使用与连接相同 TLS 策略的 reqwest 客户端（共享连接池）
+            let http_client = client.http_client().clone();
+            let url = url.clone();
+            let signed_headers = signed_headers.clone();
+            let etag = etag.clone();
+            let file = file.clone();
+            let policy = policy.clone();
+            let throttle = throttle.clone();
+            let control = control.clone();
+            in_flight.spawn(async move {
+                let what = format!("Chunk {}", chunk);
+                let bytes = control.run(policy.run(&what, || control.record_retry(), || {
+                    fetch_range(&http_client, &url, &signed_headers, &etag, offset, end, &throttle)
+                })).await?;
+
+                // Positioned write; chunks land in any order
+                let len = bytes.len() as i64;
+                tokio::task::spawn_blocking(move || {
+                    write_all_at(&file, &bytes, offset as u64)?;
+                    file.sync_data()
+                })
+                .await
+                .map_err(|e| AppError::Other(e.to_string()))??;
+
+                Ok(ModelCompletedPart {
+                    part_number: chunk,
+                    etag,
+                    size: len,
+                    md5: None,
+                })
+            });
+        }
 
-    /// Start a download task
-    pub async fn start_download(
-        &self,
-        task_id: String,
-        bucket: String,
-        object_key: String,
-        local_path: String,
-    ) -> Result<()> {
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
+        while !in_flight.is_empty() {
+            self.record_part(task, control, &mut in_flight).await?;
+        }
 
-        // Get object size
-        let head_result = client
-            .get_client()
-            .head_object()
-            .bucket(&bucket)
-            .key(&object_key)
-            .send()
+        // Chunks synced their data; flush the file's metadata before the rename
+        tokio::task::spawn_blocking(move || file.sync_all())
             .await
-            .map_err(|e| AppError::S3(e.to_string()))?;
+            .map_err(|e| AppError::Other(e.to_string()))??;
 
-        let file_size = head_result.content_length().unwrap_or(0);
+        println!("Large file download completed for task {}", task.task_id);
 
-        let mut task = TransferTask {
-            task_id: task_id.clone(),
-            task_type: TaskType::Download,
-            file_name: Path::new(&object_key)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-            local_path: local_path.clone(),
-            bucket_name: bucket.clone(),
-            object_key: object_key.clone(),
-            file_size,
-            upload_id: None,
-            part_size: PART_SIZE as i64,
-            total_parts: ((file_size as f64) / (PART_SIZE as f64)).ceil() as i32,
-            completed_parts: vec![],
-            transferred_bytes: 0,
-            status: TaskStatus::Running,
-            error_message: None,
-            use_encryption: false,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            completed_at: None,
-        };
+        Ok(())
+    }
+}
 
-        {
-            let db = self.db.lock().await;
-            db.save_task(&task)?;
-        }
+/// GET one byte range of a presigned object URL, checking it arrived in full.
+/// `signed_headers` are headers the presigned request was signed with.
+async fn fetch_range(
+    http_client: &reqwest::Client,
+    url: &str,
+    signed_headers: &reqwest::header::HeaderMap,
+    etag: &str,
+    offset: i64,
+    end: i64,
+    throttle: &Throttle,
+) -> Result<Vec<u8>> {
+    let range = format!("bytes={}-{}", offset, end);
+    let mut request = http_client
+        .get(url)
+        .headers(signed_headers.clone())
+        .header(reqwest::header::RANGE, &range);
+    if !etag.is_empty() {
+        // Fail instead of mixing ranges from two versions of the object
+        request = request.header(reqwest::header::IF_MATCH, etag);
+    }
 
-        // Download file
-        let result = if file_size < PART_SIZE as i64 {
-            println!("Starting small file download for task {}: {} bytes", task.task_id, file_size);
-            let bytes_downloaded = self.download_small_file(&task, &bucket, &object_key, &local_path).await?;
-            println!("Small file download completed for task {}: {} bytes", task.task_id, bytes_downloaded);
-            // For small files, we already updated the progress in download_small_file
-            Ok(())
-        } else {
-            println!("Starting large file download for task {}: {} bytes", task.task_id, file_size);
-            self.download_large_file(&mut task, &bucket, &object_key, &local_path).await
-        };
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| {
+            println!("HTTP request failed for range {}: {}", range, e);
+            retry::classify_http_error(&e, error_chain(&e))
+        })?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::PRECONDITION_FAILED {
+        return Err(AppError::Other("Remote object changed during download".to_string()));
+    }
+    if retry::is_retryable_status(status) {
+        return Err(AppError::Transient(format!("HTTP request failed with status: {}", status)));
+    }
+    if !status.is_success() {
+        return Err(AppError::Other(format!("HTTP request failed with status: {}", status)));
+    }
 
-        match result {
-            Ok(_) => {
-                task.status = TaskStatus::Completed;
-                task.completed_at = Some(Utc::now());
-                task.transferred_bytes = file_size;
-            }
-            Err(ref e) => {
-                task.status = TaskStatus::Failed;
-                task.error_message = Some(e.to_string());
+    // Read the body chunk by chunk so the throttle can pace the stream
+    let expected_len = end - offset + 1;
+    let mut bytes = Vec::with_capacity(expected_len as usize);
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| {
+            println!("Failed to read response bytes for range {}: {}", range, e);
+            retry::classify_http_error(&e, error_chain(&e))
+        })?
+    {
+        throttle.acquire(chunk.len()).await;
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if bytes.len() as i64 != expected_len {
+        // Usually a connection dropped mid-body
+        return Err(AppError::Transient(format!(
+            "Range {} returned {} bytes, expected {}", range, bytes.len(), expected_len
+        )));
+    }
+
+    Ok(bytes)
+}
+
+/// Merge the parts recorded for a task with those the server lists. Server
+/// parts of the expected size are kept, preferring the recorded entry (which
+/// carries the part's MD5) when the ETags agree; recorded parts the server
+/// doesn't have are dropped.
+fn reconcile_parts(task: &TransferTask, server_parts: &[ModelCompletedPart]) -> Vec<ModelCompletedPart> {
+    let mut parts: Vec<ModelCompletedPart> = server_parts
+        .iter()
+        .filter(|p| p.part_number >= 1 && p.part_number <= task.total_parts)
+        .filter(|p| {
+            let offset = (p.part_number as i64 - 1) * task.part_size;
+            p.size == task.part_size.min(task.file_size - offset)
+        })
+        .map(|server| {
+            match task.completed_parts.iter().find(|r| r.part_number == server.part_number) {
+                Some(recorded) if integrity::etags_match(&recorded.etag, &server.etag) => recorded.clone(),
+                _ => ModelCompletedPart {
+                    // For unencrypted parts the ETag is the part's MD5
+                    md5: match integrity::parse_etag(&server.etag) {
+                        EtagKind::Simple(md5) => Some(md5),
+                        _ => None,
+                    },
+                    ..server.clone()
+                },
             }
+        })
+        .collect();
+    parts.sort_by_key(|p| p.part_number);
+    parts.dedup_by_key(|p| p.part_number);
+    parts
+}
+
+/// Compare the ETag returned for an upload with the one computed locally
+fn check_upload_etag(returned: Option<&str>, expected: &str) -> Result<()> {
+    match returned {
+        Some(etag) if !integrity::etags_match(etag, expected) => Err(AppError::Integrity(format!(
+            "server ETag {} does not match computed {}", etag, expected
+        ))),
+        Some(_) => Ok(()),
+        None => {
+            println!("No ETag returned for upload; skipping verification");
+            Ok(())
         }
+    }
+}
 
-        task.updated_at = Utc::now();
-        let db = self.db.lock().await;
-        db.save_task(&task)?;
+/// Request body that releases `data` at the throttle's pace
+fn throttled_stream(data: bytes::Bytes, throttle: &Throttle) -> ByteStream {
+    ByteStream::new(SdkBody::from_body_1_x(ThrottledBody::new(data, throttle.clone())))
+}
 
-        result
+/// Write all of `buf` at `offset` without touching a shared cursor, so
+/// concurrent chunks can target the same file
+fn write_all_at(file: &std::fs::File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileExt;
+        file.write_all_at(buf, offset)
     }
-
-    async fn download_small_file(
-        &self,
-        task: &TransferTask,
-        bucket: &str,
-        object_key: &str,
-        local_path: &str,
-    ) -> Result<i64> {
-        // Check if task is cancelled before starting
-        {
-            let db = self.db.lock().await;
-            if let Some(current_task) = db.get_task(&task.task_id)? {
-                if current_task.status == TaskStatus::Cancelled {
-                    println!("Task {} is cancelled, aborting download", task.task_id);
-                    return Err(AppError::Other("Task cancelled".to_string()));
-                }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileExt;
+        let mut written = 0;
+        while written < buf.len() {
+            let n = file.seek_write(&buf[written..], offset + written as u64)?;
+            if n == 0 {
+                return Err(std::io::ErrorKind::WriteZero.into());
             }
+            written += n;
         }
+        Ok(())
+    }
+}
 
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
+/// Part size for a multipart upload. A requested size is validated against
+/// the S3 limits; otherwise parts grow with the file (rounded to whole MiB)
+/// so large uploads make fewer requests and never exceed 10,000 parts.
+fn upload_part_size(file_size: i64, requested: Option<i64>) -> Result<i64> {
+    let min_for_limit = div_ceil(file_size, MAX_PARTS);
+
+    if let Some(part_size) = requested {
+        if !(MIN_PART_SIZE..=MAX_PART_SIZE).contains(&part_size) {
+            return Err(AppError::InvalidConfig(format!(
+                "Part size must be between {} MiB and {} MiB", MIN_PART_SIZE / MIB, MAX_PART_SIZE / MIB
+            )));
+        }
+        if part_size < min_for_limit {
+            return Err(AppError::InvalidConfig(format!(
+                "Part size of {} bytes would need more than {} parts for this file", part_size, MAX_PARTS
+            )));
+        }
+        return Ok(part_size);
+    }
 
-        println!("Starting download for task {}: {} bytes", task.task_id, task.file_size);
+    let preferred = round_up_mib(div_ceil(file_size, TARGET_PARTS))
+        .clamp(MIN_PART_SIZE, DEFAULT_MAX_PART_SIZE);
+    let part_size = preferred.max(round_up_mib(min_for_limit));
+    if part_size > MAX_PART_SIZE {
+        return Err(AppError::InvalidConfig(format!(
+            "File of {} bytes exceeds the maximum multipart upload size", file_size
+        )));
+    }
+    Ok(part_size)
+}
 
-        let result = client
-            .get_client()
-            .get_object()
-            .bucket(bucket)
-            .key(object_key)
-            .send()
-            .await
-            .map_err(|e| AppError::S3(e.to_string()))?;
+/// Chunk size for ranged downloads: larger objects use larger ranges so
+/// the request count stays around 256, within 5 MB to 32 MB
+fn download_chunk_size(file_size: i64) -> i64 {
+    round_up_mib(div_ceil(file_size, 256)).clamp(MIN_PART_SIZE, 32 * MIB)
+}
 
-        let data = result.body.collect().await
-            .map_err(|e| AppError::Other(e.to_string()))?;
+fn part_count(file_size: i64, part_size: i64) -> i32 {
+    div_ceil(file_size, part_size) as i32
+}
 
-        let bytes = data.into_bytes();
-        let mut file = File::create(local_path).await?;
-        file.write_all(&bytes).await?;
+fn div_ceil(a: i64, b: i64) -> i64 {
+    (a + b - 1) / b
+}
 
-        // Update task progress in the database
-        let mut updated_task = task.clone();
-        updated_task.transferred_bytes = bytes.len() as i64;
-        updated_task.updated_at = Utc::now();
-        
-        let db = self.db.lock().await;
-        db.save_task(&updated_task)?;
-        println!("Saved task progress to database: {} bytes", updated_task.transferred_bytes);
+fn round_up_mib(bytes: i64) -> i64 {
+    div_ceil(bytes, MIB) * MIB
+}
 
-        println!("Download completed for task {}: {} bytes", task.task_id, bytes.len());
+/// Downloads are written here and renamed to the target once complete
+fn part_path(local_path: &str) -> String {
+    format!("{}.part", local_path)
+}
 
-        Ok(bytes.len() as i64)
+/// Make a rename durable by syncing the directory entry; best effort
+#[cfg(unix)]
+async fn sync_parent_dir(path: &str) {
+    if let Some(parent) = Path::new(path).parent() {
+        if let Ok(dir) = File::open(parent).await {
+            let _ = dir.sync_all().await;
+        }
     }
+}
 
-    async fn download_large_file(
-        &self,
-        task: &mut TransferTask,
-        bucket: &str,
-        object_key: &str,
-        local_path: &str,
-    ) -> Result<()> {
-        let client_guard = self.minio_client.lock().await;
-        let client = client_guard.as_ref().ok_or(AppError::NotConnected)?;
+#[cfg(not(unix))]
+async fn sync_parent_dir(_path: &str) {}
 
-        let mut file = File::create(local_path).await?;
-        let mut offset = 0i64;
+async fn file_modified(path: &str) -> Result<Option<DateTime<Utc>>> {
+    let metadata = tokio::fs::metadata(path).await?;
+    Ok(metadata.modified().ok().map(DateTime::<Utc>::from))
+}
 
-        println!("Starting large file download for task {}: {} bytes", task.task_id, task.file_size);
+fn object_modified(head: &HeadObjectOutput) -> Option<DateTime<Utc>> {
+    head.last_modified()
+        .and_then(|dt| chrono::DateTime::parse_from_rfc3339(&dt.to_string()).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
 
-        // Check if task is cancelled before starting the loop
-        {
-            let db = self.db.lock().await;
-            if let Some(current_task) = db.get_task(&task.task_id)? {
-                if current_task.status == TaskStatus::Cancelled {
-                    println!("Task {} is cancelled before download loop", task.task_id);
-                    return Err(AppError::Other("Task cancelled".to_string()));
-                }
-            }
-        }
+/// Whether an upload started before `cutoff`. Uploads of unknown age are
+/// never treated as stale.
+fn is_stale(initiated: Option<DateTime<Utc>>, cutoff: DateTime<Utc>) -> bool {
+    initiated.is_some_and(|at| at < cutoff)
+}
 
-        while offset < task.file_size {
-            // Check if task is cancelled
-            {
-                let db = self.db.lock().await;
-                if let Some(current_task) = db.get_task(&task.task_id)? {
-                    if current_task.status == TaskStatus::Cancelled {
-                        println!("Task {} is cancelled, aborting download", task.task_id);
-                        return Err(AppError::Other("Task cancelled".to_string()));
-                    }
-                }
-            }
+/// Whether a task was queued under `profile_id`. Tasks from before profiles
+/// were recorded run on whichever profile is active.
+fn belongs_to(task: &TransferTask, profile_id: Option<&str>) -> bool {
+    task.profile_id.is_none() || task.profile_id.as_deref() == profile_id
+}
 
-            let end = std::cmp::min(offset + PART_SIZE as i64 - 1, task.file_size - 1);
-            let range = format!("bytes={}-{}", offset, end);
+fn check_profile(task: &TransferTask, profile_id: Option<&str>) -> Result<()> {
+    if belongs_to(task, profile_id) {
+        Ok(())
+    } else {
+        Err(AppError::Other("Task belongs to another connection profile; switch to it to resume".to_string()))
+    }
+}
 
-            println!("Downloading range {} for task {}", range, task.task_id);
+/// Format an error together with its sources (e.g. the TLS failure behind a reqwest error)
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(inner) = source {
+        message.push_str(": ");
+        message.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    message
+}
 
-            println!("About to send range request: {}", range);
-            
-            // 使用预签名URL进行下载
-            let presigned_config = aws_sdk_s3::presigning::PresigningConfig::builder()
-                .expires_in(std::time::Duration::from_secs(3600)) // 1小时过期
-                .build()
-                .map_err(|e| AppError::S3(e.to_string()))?;
-            
-            let presigned_request = client
-                .get_client()
-                .get_object()
-                .bucket(bucket)
-                .key(object_key)
-                .range(&range)
-                .presigned(presigned_config)
-                .await
-                .map_err(|e| {
-                    println!("Failed to create presigned URL for range {}: {}", range, e);
-                    AppError::S3(e.to_string())
-                })?;
-            
-            // 使用reqwest库下载预签名URL的内容
-            let http_client = reqwest::Client::builder()
-                .danger_accept_invalid_certs(true) // 接受自签名证书
-                .build()
-                .map_err(|e| AppError::Other(e.to_string()))?;
-            
-            let response = http_client
-                .get(presigned_request.uri())
-                .send()
-                .await
-                .map_err(|e| {
-                    println!("HTTP request failed for range {}: {}", range, e);
-                    AppError::Other(e.to_string())
-                })?;
-            
-            if !response.status().is_success() {
-                return Err(AppError::Other(format!("HTTP request failed with status: {}", response.status())));
-            }
-            
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|e| {
-                    println!("Failed to read response bytes for range {}: {}", range, e);
-                    AppError::Other(e.to_string())
-                })?;
-            
-            println!("Successfully downloaded {} bytes for range {}", bytes.len(), range);
-
-            println!("Range request completed for task {}: {}", task.task_id, range);
-            
-            file.write_all(&bytes).await?;
-            println!("Wrote {} bytes to file", bytes.len());
-
-            task.transferred_bytes += bytes.len() as i64;
-            task.updated_at = Utc::now();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            {
-                let db = self.db.lock().await;
-                db.save_task(task)?;
-                println!("Progress updated for task {}: {}/{} bytes ({}%)", task.task_id, task.transferred_bytes, task.file_size, (task.transferred_bytes as f64 / task.file_size as f64 * 100.0) as i32);
-            }
+    #[test]
+    fn test_default_part_size_small_files() {
+        assert_eq!(upload_part_size(0, None).unwrap(), MIN_PART_SIZE);
+        assert_eq!(upload_part_size(100 * MIB, None).unwrap(), MIN_PART_SIZE);
+    }
 
-            offset = end + 1;
-            println!("Updated offset to {}, total file size: {}", offset, task.file_size);
+    #[test]
+    fn test_default_part_size_stays_under_part_limit() {
+        for file_size in [10 * 1024 * MIB, 60 * 1024 * MIB, 1024 * 1024 * MIB, 5 * 1024 * 1024 * MIB] {
+            let part_size = upload_part_size(file_size, None).unwrap();
+            assert!((MIN_PART_SIZE..=MAX_PART_SIZE).contains(&part_size));
+            assert!(part_count(file_size, part_size) as i64 <= MAX_PARTS, "{} bytes", file_size);
+            assert_eq!(part_size % MIB, 0);
         }
+    }
 
-        println!("Large file download completed for task {}", task.task_id);
+    #[test]
+    fn test_requested_part_size_is_validated() {
+        assert_eq!(upload_part_size(100 * MIB, Some(8 * MIB)).unwrap(), 8 * MIB);
+        assert!(upload_part_size(100 * MIB, Some(MIB)).is_err());
+        assert!(upload_part_size(100 * MIB, Some(6 * 1024 * MIB)).is_err());
+        // 100 GiB in 5 MiB parts would need 20,480 parts
+        assert!(upload_part_size(100 * 1024 * MIB, Some(MIN_PART_SIZE)).is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn test_reconcile_parts() {
+        let part = |part_number, etag: &str, size, md5: Option<&str>| ModelCompletedPart {
+            part_number,
+            etag: etag.to_string(),
+            size,
+            md5: md5.map(|m| m.to_string()),
+        };
+        let md5_a = "0cc175b9c0f1b6a831c399e269772661";
+        let md5_c = "4a8a08f09d37b73795649038408b5f33";
+
+        let mut task = TransferTask {
+            file_size: 12 * MIB,
+            part_size: 5 * MIB,
+            total_parts: 3,
+            completed_parts: vec![
+                part(1, &format!("\"{}\"", md5_a), 5 * MIB, Some(md5_a)),
+                // Recorded, but lost by the server
+                part(2, "\"lost\"", 5 * MIB, Some(md5_a)),
+            ],
+            ..serde_json::from_str::<TransferTask>(TASK_JSON).unwrap()
+        };
+        let server = vec![
+            part(1, md5_a, 5 * MIB, None),
+            // Finished after the last checkpoint
+            part(3, md5_c, 2 * MIB, None),
+            // Wrong size for its position
+            part(2, md5_c, MIB, None),
+        ];
+
+        task.completed_parts = reconcile_parts(&task, &server);
+        let numbers: Vec<i32> = task.completed_parts.iter().map(|p| p.part_number).collect();
+        assert_eq!(numbers, vec![1, 3]);
+        assert_eq!(task.completed_parts[0].md5.as_deref(), Some(md5_a));
+        assert_eq!(task.completed_parts[1].md5.as_deref(), Some(md5_c));
+    }
+
+    const TASK_JSON: &str = r#"{
+        "task_id": "t", "task_type": "upload", "file_name": "f", "local_path": "f",
+        "bucket_name": "b", "object_key": "k", "file_size": 0, "upload_id": "u",
+        "part_size": 0, "total_parts": 0, "completed_parts": [], "transferred_bytes": 0,
+        "status": "interrupted", "error_message": null, "use_encryption": false,
+        "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z",
+        "completed_at": null
+    }"#;
+
+    #[test]
+    fn test_tasks_only_run_on_their_profile() {
+        let mut task = serde_json::from_str::<TransferTask>(TASK_JSON).unwrap();
+        // Tasks from before profiles were recorded run anywhere
+        assert!(belongs_to(&task, Some("home")));
+        assert!(belongs_to(&task, None));
+
+        task.profile_id = Some("home".to_string());
+        assert!(belongs_to(&task, Some("home")));
+        assert!(!belongs_to(&task, Some("work")));
+        assert!(!belongs_to(&task, None));
+    }
+
+    #[test]
+    fn test_stale_uploads() {
+        let cutoff = Utc::now();
+        assert!(is_stale(Some(cutoff - chrono::Duration::hours(1)), cutoff));
+        assert!(!is_stale(Some(cutoff), cutoff));
+        assert!(!is_stale(None, cutoff));
+    }
+
+    #[test]
+    fn test_download_chunk_size_bounds() {
+        assert_eq!(download_chunk_size(MIB), MIN_PART_SIZE);
+        assert_eq!(download_chunk_size(100 * 1024 * MIB), 32 * MIB);
     }
 }
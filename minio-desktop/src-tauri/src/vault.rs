@@ -0,0 +1,224 @@
+// Master passphrase vault for secrets stored in the local database.
+// Keys are derived with Argon2id and values sealed with XChaCha20-Poly1305.
+
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, KeyInit, aead::{Aead, Payload}};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+use crate::error::{AppError, Result};
+
+/// Prefix marking a value sealed by the vault; anything else is legacy plaintext
+const SEALED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Argon2id cost parameters, persisted so they can be raised later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost_kib: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // OWASP recommended minimum for Argon2id
+        Self { m_cost_kib: 19 * 1024, t_cost: 2, p_cost: 1 }
+    }
+}
+
+/// Derive a 256-bit key from the passphrase
+pub fn derive_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let argon_params = Params::new(params.m_cost_kib, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| AppError::Encryption(format!("Invalid KDF parameters: {}", e)))?;
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params);
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon2
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Encryption(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+/// Generate a random 16-byte salt
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+/// Whether a stored value was sealed by the vault
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/// In-memory vault state. The key is only held while unlocked and is
+/// dropped (and zeroed) on lock or after the idle timeout.
+pub struct Vault {
+    configured: Cell<bool>,
+    key: RefCell<Option<Zeroizing<[u8; 32]>>>,
+    last_used: Cell<Instant>,
+    idle_timeout: Cell<Duration>,
+}
+
+impl Vault {
+    pub fn new(configured: bool) -> Self {
+        Self {
+            configured: Cell::new(configured),
+            key: RefCell::new(None),
+            last_used: Cell::new(Instant::now()),
+            idle_timeout: Cell::new(DEFAULT_IDLE_TIMEOUT),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.configured.get()
+    }
+
+    pub fn set_configured(&self, configured: bool) {
+        self.configured.set(configured);
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.lock_if_idle();
+        self.key.borrow().is_some()
+    }
+
+    pub fn unlock(&self, key: Zeroizing<[u8; 32]>) {
+        *self.key.borrow_mut() = Some(key);
+        self.last_used.set(Instant::now());
+    }
+
+    pub fn lock(&self) {
+        self.key.borrow_mut().take();
+    }
+
+    /// Lock the vault if it has not been used within the idle timeout.
+    /// Returns true if this call locked it.
+    pub fn lock_if_idle(&self) -> bool {
+        let expired = self.last_used.get().elapsed() >= self.idle_timeout.get();
+        if expired && self.key.borrow().is_some() {
+            self.lock();
+            eprintln!("[Vault] Locked after idle timeout");
+            return true;
+        }
+        false
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout.get()
+    }
+
+    pub fn set_idle_timeout(&self, timeout: Duration) {
+        self.idle_timeout.set(timeout);
+    }
+
+    /// Encrypt a value. `context` is bound as associated data so a sealed
+    /// value cannot be moved to another row or column.
+    pub fn seal(&self, plaintext: &str, context: &str) -> Result<String> {
+        let cipher = self.cipher()?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = cipher
+            .encrypt(&XNonce::from(nonce), Payload { msg: plaintext.as_bytes(), aad: context.as_bytes() })
+            .map_err(|_| AppError::Encryption("Failed to encrypt secret".to_string()))?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", SEALED_PREFIX, general_purpose::STANDARD.encode(blob)))
+    }
+
+    /// Decrypt a value produced by `seal`. Plaintext (unsealed) values are
+    /// returned unchanged so rows written before the vault existed still load.
+    pub fn open(&self, stored: &str, context: &str) -> Result<String> {
+        let Some(encoded) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+
+        let cipher = self.cipher()?;
+        let blob = general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| AppError::Encryption(format!("Corrupted secret: {}", e)))?;
+        if blob.len() < NONCE_LEN {
+            return Err(AppError::Encryption("Corrupted secret: too short".to_string()));
+        }
+
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into()
+            .map_err(|_| AppError::Encryption("Corrupted secret: bad nonce".to_string()))?;
+        let plaintext = cipher
+            .decrypt(&XNonce::from(nonce), Payload { msg: ciphertext, aad: context.as_bytes() })
+            .map_err(|_| AppError::Encryption("Failed to decrypt secret (wrong passphrase or tampered data)".to_string()))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|e| AppError::Encryption(format!("Corrupted secret: {}", e)))
+    }
+
+    fn cipher(&self) -> Result<XChaCha20Poly1305> {
+        self.lock_if_idle();
+        let key = self.key.borrow();
+        let key = key.as_ref().ok_or(AppError::VaultLocked)?;
+        self.last_used.set(Instant::now());
+        XChaCha20Poly1305::new_from_slice(key.as_ref())
+            .map_err(|e| AppError::Encryption(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_params() -> KdfParams {
+        // Cheap parameters to keep the tests fast
+        KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 }
+    }
+
+    #[test]
+    fn test_seal_and_open_roundtrip() {
+        let vault = Vault::new(true);
+        vault.unlock(derive_key("passphrase", b"0123456789abcdef", &test_params()).unwrap());
+
+        let sealed = vault.seal("secret", "ctx").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(vault.open(&sealed, "ctx").unwrap(), "secret");
+        assert!(vault.open(&sealed, "other").is_err());
+    }
+
+    #[test]
+    fn test_locked_vault_rejects_sealed_values() {
+        let vault = Vault::new(true);
+        vault.unlock(derive_key("passphrase", b"0123456789abcdef", &test_params()).unwrap());
+        let sealed = vault.seal("secret", "ctx").unwrap();
+
+        vault.lock();
+        assert!(matches!(vault.open(&sealed, "ctx"), Err(AppError::VaultLocked)));
+        // Legacy plaintext is passed through even while locked
+        assert_eq!(vault.open("plain", "ctx").unwrap(), "plain");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails() {
+        let salt = b"0123456789abcdef";
+        let vault = Vault::new(true);
+        vault.unlock(derive_key("right", salt, &test_params()).unwrap());
+        let sealed = vault.seal("secret", "ctx").unwrap();
+
+        vault.unlock(derive_key("wrong", salt, &test_params()).unwrap());
+        assert!(vault.open(&sealed, "ctx").is_err());
+    }
+
+    #[test]
+    fn test_idle_timeout_locks() {
+        let vault = Vault::new(true);
+        vault.unlock(derive_key("passphrase", b"0123456789abcdef", &test_params()).unwrap());
+        vault.set_idle_timeout(Duration::from_secs(0));
+        assert!(vault.lock_if_idle());
+        assert!(!vault.is_unlocked());
+    }
+}
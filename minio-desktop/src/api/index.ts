@@ -1,4 +1,13 @@
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+export type TlsMode = 'system' | 'custom_ca' | 'pinned' | 'insecure'
+
+export interface TlsPolicy {
+  mode: TlsMode
+  ca_bundle_pem?: string
+  pinned_sha256?: string[]
+}
 
 export interface ConnectionConfig {
   endpoint: string
@@ -6,9 +15,40 @@ export interface ConnectionConfig {
   accessKey: string
   secretKey: string
   useSsl: boolean
+  tls?: TlsPolicy
   lastConnected?: string
 }
 
+export interface ConnectionProfile {
+  profile_id: string
+  name: string
+  endpoint: string
+  port: number
+  access_key: string
+  secret_key: string
+  use_ssl: boolean
+  tls: TlsPolicy
+  is_default: boolean
+  last_connected?: string
+  created_at: string
+  updated_at: string
+}
+
+export interface ProfileInput {
+  endpoint: string
+  port: number
+  access_key: string
+  secret_key: string
+  use_ssl: boolean
+  tls?: TlsPolicy
+}
+
+export interface VaultStatus {
+  configured: boolean
+  unlocked: boolean
+  idle_timeout_secs: number
+}
+
 export interface BucketInfo {
   name: string
   creation_date?: string
@@ -20,10 +60,95 @@ export interface ObjectInfo {
   last_modified?: string
   content_type?: string
   is_dir: boolean
+  etag?: string
+}
+
+export type VersioningStatus = 'disabled' | 'enabled' | 'suspended'
+
+// One version of an object, or a delete marker
+export interface ObjectVersionInfo {
+  key: string
+  version_id: string
+  is_latest: boolean
+  is_delete_marker: boolean
+  size: number
+  last_modified?: string
+  etag?: string
+}
+
+export interface ObjectVersionPage {
+  versions: ObjectVersionInfo[]
+  next_key_marker?: string
+  next_version_id_marker?: string
+  is_truncated: boolean
+}
+
+export interface LifecycleTag {
+  key: string
+  value: string
+}
+
+// A bucket lifecycle rule; an empty prefix and no tags cover the whole bucket
+export interface LifecycleRule {
+  id: string
+  enabled: boolean
+  prefix?: string
+  tags: LifecycleTag[]
+  object_size_greater_than?: number
+  object_size_less_than?: number
+  expiration_days?: number
+  expiration_date?: string
+  expired_object_delete_marker: boolean
+  noncurrent_expiration_days?: number
+  newer_noncurrent_versions?: number
+  abort_incomplete_multipart_days?: number
+  // Storage class transitions, kept from the server's rule on save
+  has_transitions?: boolean
+}
+
+// An incomplete multipart upload still holding parts on the server
+export interface MultipartUploadInfo {
+  bucket: string
+  key: string
+  upload_id: string
+  initiated?: string
+  size?: number
+  part_count?: number
+  task_id?: string
+}
+
+export interface ObjectListPage {
+  objects: ObjectInfo[]
+  next_continuation_token?: string
+  is_truncated: boolean
+}
+
+export interface ObjectListBatch {
+  listing_id: string
+  objects: ObjectInfo[]
+  total: number
+  done: boolean
+  error?: string
+}
+
+// What a transfer does when the local file or object key already exists
+export type ConflictPolicy = 'overwrite' | 'skip' | 'keep_both' | 'overwrite_if_newer'
+
+// How a transfer is queued; anything left out takes its default
+export interface TransferOptions {
+  use_encryption?: boolean
+  // With use_encryption and no key the stored key is used
+  encryption_key?: string
+  part_size?: number
+  priority?: number
+  conflict_policy?: ConflictPolicy
+  // Object version a download fetches; the latest when left out
+  version_id?: string
 }
 
 export interface TransferTask {
   task_id: string
+  profile_id?: string
   task_type: 'upload' | 'download'
   file_name: string
   local_path: string
@@ -31,10 +156,87 @@ export interface TransferTask {
   object_key: string
   file_size: number
   transferred_bytes: number
-  status: 'pending' | 'running' | 'paused' | 'completed' | 'failed' | 'cancelled'
+  status: 'pending' | 'running' | 'paused' | 'completed' | 'failed' | 'cancelled' | 'corrupted' | 'interrupted' | 'skipped'
   error_message?: string
   created_at: string
   updated_at: string
+  priority: number
+  queue_position: number
+  remote_etag?: string
+  job_id?: string
+  retry_count: number
+  bandwidth_limit?: number
+  encryption_key_md5?: string
+  conflict_policy: ConflictPolicy
+  source_fingerprint?: { size: number; modified_ms?: number; sample_md5: string }
+  version_id?: string
+  active_ms: number
+  average_speed: number
+  speed: number
+  eta_seconds?: number
+}
+
+// Payload of 'transfer-progress' events
+export interface TransferProgress {
+  task_id: string
+  job_id?: string
+  task_type: TransferTask['task_type']
+  status: TransferTask['status']
+  file_size: number
+  transferred_bytes: number
+  completed_parts: number
+  total_parts: number
+  retry_count: number
+  error_message?: string
+  active_ms: number
+  average_speed: number
+  speed: number
+  eta_seconds?: number
+}
+
+export interface TaskThroughput {
+  task_id: string
+  transferred_bytes: number
+  active_ms: number
+  average_speed: number
+  speed: number
+  eta_seconds?: number
+}
+
+// Payload of 'transfer-stats' events, sent every second while tasks run
+export interface TransferStats {
+  upload_speed: number
+  download_speed: number
+  running_tasks: number
+  remaining_bytes: number
+  eta_seconds?: number
+  tasks: TaskThroughput[]
+}
+
+export interface TransferJob {
+  job_id: string
+  job_type: 'upload' | 'download'
+  name: string
+  local_path: string
+  bucket_name: string
+  prefix: string
+  created_at: string
+  file_count: number
+  completed_count: number
+  failed_count: number
+  total_bytes: number
+  transferred_bytes: number
+  status: TransferTask['status']
+}
+
+export interface TransferSettings {
+  max_concurrent_tasks: number
+  max_concurrent_parts: number
+  retry_attempts: number
+  upload_limit?: number | null
+  download_limit?: number | null
+  // Queue tasks interrupted by quitting the app again on the next start
+  resume_interrupted?: boolean
 }
 
 export const api = {
@@ -45,7 +247,8 @@ export const api = {
       port: config.port,
       accessKey: config.accessKey,
       secretKey: config.secretKey,
-      useSsl: config.useSsl
+      useSsl: config.useSsl,
+      tls: config.tls
     })
   },
 
@@ -55,7 +258,8 @@ export const api = {
       port: config.port,
       accessKey: config.accessKey,
       secretKey: config.secretKey,
-      useSsl: config.useSsl
+      useSsl: config.useSsl,
+      tls: config.tls
     })
   },
 
@@ -63,6 +267,64 @@ export const api = {
     return invoke('get_connection')
   },
 
+  // Connection profiles
+  async listProfiles(): Promise<ConnectionProfile[]> {
+    return invoke('list_profiles')
+  },
+
+  async getActiveProfile(): Promise<ConnectionProfile | null> {
+    return invoke('get_active_profile')
+  },
+
+  async createProfile(name: string, profile: ProfileInput): Promise<ConnectionProfile> {
+    return invoke('create_profile', { name, input: profile })
+  },
+
+  async updateProfile(profileId: string, profile: ProfileInput): Promise<ConnectionProfile> {
+    return invoke('update_profile', { profileId, input: profile })
+  },
+
+  async renameProfile(profileId: string, name: string): Promise<void> {
+    return invoke('rename_profile', { profileId, name })
+  },
+
+  async deleteProfile(profileId: string): Promise<void> {
+    return invoke('delete_profile', { profileId })
+  },
+
+  async duplicateProfile(profileId: string, name?: string): Promise<ConnectionProfile> {
+    return invoke('duplicate_profile', { profileId, name })
+  },
+
+  async setDefaultProfile(profileId: string): Promise<void> {
+    return invoke('set_default_profile', { profileId })
+  },
+
+  async switchProfile(profileId: string): Promise<ConnectionProfile> {
+    return invoke('switch_profile', { profileId })
+  },
+
+  // Vault
+  async getVaultStatus(): Promise<VaultStatus> {
+    return invoke('get_vault_status')
+  },
+
+  async setupVault(passphrase: string): Promise<VaultStatus> {
+    return invoke('setup_vault', { passphrase })
+  },
+
+  async unlockVault(passphrase: string): Promise<VaultStatus> {
+    return invoke('unlock_vault', { passphrase })
+  },
+
+  async lockVault(): Promise<void> {
+    return invoke('lock_vault')
+  },
+
+  async setVaultIdleTimeout(seconds: number): Promise<void> {
+    return invoke('set_vault_idle_timeout', { seconds })
+  },
+
   // Buckets
   async listBuckets(): Promise<BucketInfo[]> {
     return invoke('list_buckets')
@@ -76,40 +338,163 @@ export const api = {
     return invoke('delete_bucket', { name })
   },
 
+  async getBucketVersioning(name: string): Promise<VersioningStatus> {
+    return invoke('get_bucket_versioning', { name })
+  },
+
+  // false suspends versioning; existing versions are kept
+  async setBucketVersioning(name: string, enabled: boolean): Promise<void> {
+    return invoke('set_bucket_versioning', { name, enabled })
+  },
+
+  async getBucketLifecycle(name: string): Promise<LifecycleRule[]> {
+    return invoke('get_bucket_lifecycle', { name })
+  },
+
+  // Replaces every rule; an empty list removes the configuration
+  async putBucketLifecycle(name: string, rules: LifecycleRule[]): Promise<void> {
+    return invoke('put_bucket_lifecycle', { name, rules })
+  },
+
+  async deleteBucketLifecycle(name: string): Promise<void> {
+    return invoke('delete_bucket_lifecycle', { name })
+  },
+
   // Objects
-  async listObjects(bucket: string, prefix?: string): Promise<ObjectInfo[]> {
-    return invoke('list_objects', { bucket, prefix })
+  async listObjects(bucket: string, prefix?: string, startAfter?: string): Promise<ObjectInfo[]> {
+    return invoke('list_objects', { bucket, prefix, startAfter })
+  },
+
+  async listObjectsPage(
+    bucket: string,
+    options: { prefix?: string; continuationToken?: string; startAfter?: string; maxKeys?: number; recursive?: boolean } = {}
+  ): Promise<ObjectListPage> {
+    return invoke('list_objects_page', { bucket, ...options })
+  },
+
+  // Results arrive as 'object-list-batch' events carrying the returned listing id
+  async streamObjects(
+    bucket: string,
+    options: { prefix?: string; startAfter?: string; recursive?: boolean; batchSize?: number } = {}
+  ): Promise<string> {
+    return invoke('stream_objects', { bucket, ...options })
+  },
+
+  async cancelObjectListing(listingId: string): Promise<void> {
+    return invoke('cancel_object_listing', { listingId })
   },
 
   async uploadFile(
     localPath: string, 
     bucket: string, 
     objectKey: string,
-    useEncryption?: boolean,
-    encryptionKey?: string
+    options?: TransferOptions
   ): Promise<string> {
     return invoke('upload_file', { 
       localPath, 
       bucket, 
       objectKey,
-      useEncryption,
-      encryptionKey
+      options
+    })
+  },
+
+  async uploadFolder(
+    localPath: string,
+    bucket: string,
+    prefix?: string,
+    followSymlinks?: boolean,
+    options?: TransferOptions
+  ): Promise<string> {
+    return invoke('upload_folder', {
+      localPath,
+      bucket,
+      prefix,
+      followSymlinks,
+      options
     })
   },
 
-  async downloadFile(bucket: string, objectKey: string, localPath: string): Promise<string> {
-    return invoke('download_file', { bucket, objectKey, localPath })
+  // SSE-C objects need their key
+  async downloadFile(
+    bucket: string,
+    objectKey: string,
+    localPath: string,
+    options?: TransferOptions
+  ): Promise<string> {
+    return invoke('download_file', { bucket, objectKey, localPath, options })
+  },
+
+  async downloadFolder(
+    bucket: string,
+    prefix: string,
+    localPath: string,
+    options?: TransferOptions
+  ): Promise<string> {
+    return invoke('download_folder', { bucket, prefix, localPath, options })
   },
 
   async deleteObject(bucket: string, key: string): Promise<void> {
     return invoke('delete_object', { bucket, key })
   },
 
+  // Versions and delete markers, newest first per key; pass the markers back for the next page
+  async listObjectVersions(
+    bucket: string,
+    prefix?: string,
+    keyMarker?: string,
+    versionIdMarker?: string,
+    maxKeys?: number
+  ): Promise<ObjectVersionPage> {
+    return invoke('list_object_versions', { bucket, prefix, keyMarker, versionIdMarker, maxKeys })
+  },
+
+  // Copies the version over the latest one
+  async restoreObjectVersion(
+    bucket: string,
+    key: string,
+    versionId: string,
+    useEncryption?: boolean,
+    encryptionKey?: string
+  ): Promise<void> {
+    return invoke('restore_object_version', { bucket, key, versionId, useEncryption, encryptionKey })
+  },
+
+  async deleteObjectVersion(bucket: string, key: string, versionId: string): Promise<void> {
+    return invoke('delete_object_version', { bucket, key, versionId })
+  },
+
+  // Multipart uploads left behind by failed or deleted transfers
+  async listMultipartUploads(bucket: string): Promise<MultipartUploadInfo[]> {
+    return invoke('list_multipart_uploads', { bucket })
+  },
+
+  async abortMultipartUpload(bucket: string, key: string, uploadId: string): Promise<void> {
+    return invoke('abort_multipart_upload', { bucket, key, uploadId })
+  },
+
+  // Returns how many uploads were aborted
+  async abortStaleMultipartUploads(bucket: string, olderThanHours: number): Promise<number> {
+    return invoke('abort_stale_multipart_uploads', { bucket, olderThanHours })
+  },
+
   // Transfers
   async getTransferTasks(): Promise<TransferTask[]> {
     return invoke('get_transfer_tasks')
   },
 
+  // Sent as parts finish and whenever a task changes status
+  async onTransferProgress(handler: (progress: TransferProgress) => void): Promise<UnlistenFn> {
+    return listen<TransferProgress>('transfer-progress', event => handler(event.payload))
+  },
+
+  async getTransferStats(): Promise<TransferStats> {
+    return invoke('get_transfer_stats')
+  },
+
+  async onTransferStats(handler: (stats: TransferStats) => void): Promise<UnlistenFn> {
+    return listen<TransferStats>('transfer-stats', event => handler(event.payload))
+  },
+
   async pauseTask(taskId: string): Promise<void> {
     return invoke('pause_task', { taskId })
   },
@@ -118,6 +503,10 @@ export const api = {
     return invoke('resume_task', { taskId })
   },
 
+  async resumeDownload(taskId: string): Promise<void> {
+    return invoke('resume_download', { taskId })
+  },
+
   async cancelTask(taskId: string): Promise<void> {
     return invoke('cancel_task', { taskId })
   },
@@ -126,6 +515,50 @@ export const api = {
     return invoke('cancel_all_tasks')
   },
 
+  async getTransferJobs(): Promise<TransferJob[]> {
+    return invoke('get_transfer_jobs')
+  },
+
+  async getJobTasks(jobId: string): Promise<TransferTask[]> {
+    return invoke('get_job_tasks', { jobId })
+  },
+
+  async pauseJob(jobId: string): Promise<void> {
+    return invoke('pause_job', { jobId })
+  },
+
+  async resumeJob(jobId: string): Promise<void> {
+    return invoke('resume_job', { jobId })
+  },
+
+  async cancelJob(jobId: string): Promise<void> {
+    return invoke('cancel_job', { jobId })
+  },
+
+  async deleteJob(jobId: string): Promise<void> {
+    return invoke('delete_job', { jobId })
+  },
+
+  async setTaskPriority(taskId: string, priority: number): Promise<void> {
+    return invoke('set_task_priority', { taskId, priority })
+  },
+
+  async setTaskBandwidthLimit(taskId: string, limit: number | null): Promise<void> {
+    return invoke('set_task_bandwidth_limit', { taskId, limit })
+  },
+
+  async reorderTasks(taskIds: string[]): Promise<void> {
+    return invoke('reorder_tasks', { taskIds })
+  },
+
+  async getTransferSettings(): Promise<TransferSettings> {
+    return invoke('get_transfer_settings')
+  },
+
+  async updateTransferSettings(settings: TransferSettings): Promise<void> {
+    return invoke('update_transfer_settings', { settings })
+  },
+
   async deleteTask(taskId: string): Promise<void> {
     return invoke('delete_task', { taskId })
   },
@@ -135,15 +568,15 @@ export const api = {
   },
 
   // Streaming
-  async getStreamUrl(bucket: string, objectKey: string): Promise<string> {
-    return invoke('get_stream_url', { bucket, objectKey })
+  async getStreamUrl(bucket: string, objectKey: string, versionId?: string): Promise<string> {
+    return invoke('get_stream_url', { bucket, objectKey, versionId })
   },
 
-  async getPresignedUrl(bucket: string, objectKey: string, expiresInSeconds?: number): Promise<string> {
-    return invoke('get_presigned_url', { bucket, objectKey, expiresInSeconds })
+  async getPresignedUrl(bucket: string, objectKey: string, expiresInSeconds?: number, versionId?: string): Promise<string> {
+    return invoke('get_presigned_url', { bucket, objectKey, expiresInSeconds, versionId })
   },
 
-  async getObjectData(bucket: string, objectKey: string): Promise<Uint8Array> {
-    return invoke('get_object_data', { bucket, objectKey })
+  async getObjectData(bucket: string, objectKey: string, versionId?: string): Promise<Uint8Array> {
+    return invoke('get_object_data', { bucket, objectKey, versionId })
   }
 }
@@ -1,11 +1,14 @@
 import { defineStore } from 'pinia'
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/tauri'
+import type { ConflictPolicy, TransferOptions } from '../api'
 
 export const useSettingsStore = defineStore('settings', () => {
   const showDeleteButton = ref(true)
   const enableEncryption = ref(false)
   const encryptionKey = ref('')
+  // 目标已存在时的处理方式（上传和下载共用）
+  const conflictPolicy = ref<ConflictPolicy>('overwrite')
 
   // 从本地存储加载设置
   async function loadSettings() {
@@ -29,9 +32,11 @@ export const useSettingsStore = defineStore('settings', () => {
       try {
         const settings = JSON.parse(saved)
         showDeleteButton.value = settings.showDeleteButton ?? true
+        conflictPolicy.value = settings.conflictPolicy ?? 'overwrite'
       } catch (e) {
         console.error('Failed to load settings:', e)
         showDeleteButton.value = true
+        conflictPolicy.value = 'overwrite'
       }
     }
   }
@@ -39,7 +44,8 @@ export const useSettingsStore = defineStore('settings', () => {
   // 保存设置到本地存储
   function saveSettings() {
     const settings = {
-      showDeleteButton: showDeleteButton.value
+      showDeleteButton: showDeleteButton.value,
+      conflictPolicy: conflictPolicy.value
       // 不保存enableEncryption和encryptionKey到localStorage，它们在数据库中
     }
     localStorage.setItem('app-settings', JSON.stringify(settings))
@@ -57,6 +63,15 @@ export const useSettingsStore = defineStore('settings', () => {
     }
   }
 
+  // 按当前设置生成传输选项
+  function transferOptions(): TransferOptions {
+    return {
+      use_encryption: enableEncryption.value,
+      encryption_key: enableEncryption.value ? encryptionKey.value : undefined,
+      conflict_policy: conflictPolicy.value
+    }
+  }
+
   // 切换删除按钮显示状态
   function toggleDeleteButton() {
     showDeleteButton.value = !showDeleteButton.value
@@ -70,6 +85,8 @@ export const useSettingsStore = defineStore('settings', () => {
     showDeleteButton,
     enableEncryption,
     encryptionKey,
+    conflictPolicy,
+    transferOptions,
     toggleDeleteButton,
     toggleEncryption,
     saveSettings,